@@ -1,61 +1,144 @@
 use crate::value::{Unit, Value};
+use serde_json::json;
 
-fn format_items(results: Vec<String>) -> String {
+/// The default subtitle of each item, `{value}` is replaced with the item's value.
+pub const DEFAULT_SUBTITLE: &str = r#"copy+paste as "{value}""#;
+
+fn format_items(results: Vec<String>, subtitle: &str) -> String {
   let items = results
     .into_iter()
     .map(|result| {
-      format!(
-        r#"{{
-        "arg": "{0}",
+      json!({
+        "arg": result,
         "valid": "YES",
-        "autocomplete": "{0}",
+        "autocomplete": result,
         "type": "default",
-        "title": "{0}",
-        "subtitle": "copy+paste as \"{0}\""
-      }}"#,
-        result
-      )
+        "title": result,
+        "subtitle": subtitle.replace("{value}", &result),
+      })
     })
     .collect::<Vec<_>>();
 
-  format!(r#"{{"items": [{}]}}"#, items.join(","))
+  json!({ "items": items }).to_string()
 }
 
-pub fn alfred_result(value: Value) -> String {
+/// Formats the value as alfred items, with any of the favorite units listed first.
+pub fn alfred_result(value: Value, subtitle: &str, favorites: &[Unit]) -> String {
   if value.is_raw() {
     let results = if value.is_integer() {
       vec![
         format!("{}", value.number),
-        format!("{:#x}", value.number),
+        value.number.as_hex_string(),
         format!("{:#o}", value.number),
         format!("{:#b}", value.number),
       ]
     } else {
       vec![format!("{}", value.number.as_pretty_string())]
     };
-    format_items(results)
+    format_items(results, subtitle)
   } else {
-    let units = Unit::for_group(value.unit.group());
-    let results = units[..usize::min(units.len(), 4)]
+    let results = Unit::for_group_with_favorites(value.unit.group(), favorites)
       .iter()
       .map(|unit| format!("{}", value.convert(*unit).unwrap()))
       .collect::<Vec<_>>();
-    format_items(results)
+    format_items(results, subtitle)
   }
 }
 
 pub fn alfred_error(err: String) -> String {
-  format!(
-    r#"{{"items": [
-      {{
-        "arg": "...",
-        "valid": "NO",
-        "autocomplete": "...",
-        "type": "default",
-        "title": "{0}",
-        "subtitle": "..."
-      }}
-    ]}}"#,
-    err
-  )
+  json!({
+    "items": [{
+      "arg": "...",
+      "valid": "NO",
+      "autocomplete": "...",
+      "type": "default",
+      "title": err,
+      "subtitle": "...",
+    }]
+  })
+  .to_string()
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::value::{Number, Width};
+
+  fn subtitles(json: &str) -> Vec<String> {
+    let json: serde_json::Value = serde_json::from_str(json).unwrap();
+    let items = json["items"].as_array().unwrap();
+    items
+      .iter()
+      .map(|item| item["subtitle"].as_str().unwrap().to_string())
+      .collect()
+  }
+
+  #[test]
+  fn test_alfred_subtitle() {
+    let value = Value::new_integer(255, Width::U64);
+    let json = alfred_result(value.clone(), DEFAULT_SUBTITLE, &[]);
+    assert!(subtitles(&json).contains(&r#"copy+paste as "0xff""#.to_string()));
+
+    let json = alfred_result(value, "{value} of 255", &[]);
+    assert!(subtitles(&json).contains(&"0xff of 255".to_string()));
+  }
+
+  #[test]
+  fn test_alfred_all_units() {
+    let value = Value::new(Number::from(1u64), Unit::Gigabyte);
+    assert_eq!(
+      subtitles(&alfred_result(value, "{value}", &[])),
+      [
+        "1073741824B",
+        "1048576K",
+        "1024M",
+        "1G",
+        "0.0009765625T",
+        "0.00000095367431640625P"
+      ]
+    );
+
+    let value = Value::new_integer(255, Width::U64);
+    assert_eq!(subtitles(&alfred_result(value, "{value}", &[])).len(), 4);
+  }
+
+  #[test]
+  fn test_alfred_favorites() {
+    let favorites = [Unit::Megabyte, Unit::Gigabyte, Unit::Fahrenheit];
+    let value = Value::new(Number::from(1u64), Unit::Gigabyte);
+    assert_eq!(
+      subtitles(&alfred_result(value, "{value}", &favorites)),
+      [
+        "1024M",
+        "1G",
+        "1073741824B",
+        "1048576K",
+        "0.0009765625T",
+        "0.00000095367431640625P"
+      ]
+    );
+
+    let value = Value::new(Number::from(100u64), Unit::Celsius);
+    assert_eq!(subtitles(&alfred_result(value, "{value}", &favorites))[0], "212°F");
+  }
+
+  #[test]
+  fn test_alfred_precision() {
+    use crate::settings::{set_settings, Settings};
+
+    set_settings(Settings {
+      precision: Some(5),
+      ..Settings::default()
+    });
+    let value = Value::new_float(std::f64::consts::PI);
+    assert_eq!(subtitles(&alfred_result(value, "{value}", &[])), ["3.14159"]);
+    set_settings(Settings::default());
+  }
+
+  #[test]
+  fn test_alfred_error_escaped() {
+    let err = r#"Unknown identifier "foo\bar"	"#.to_string();
+    let json: serde_json::Value = serde_json::from_str(&alfred_error(err.clone())).unwrap();
+    assert_eq!(json["items"][0]["title"], err.as_str());
+  }
 }
@@ -0,0 +1,55 @@
+use crate::lexer::Spanned;
+use crate::settings::settings;
+use crate::value::Value;
+use std::cell::RefCell;
+use std::collections::HashMap;
+
+/// A single-parameter function defined with `name(param) = body`.
+#[derive(Clone, Debug)]
+pub(crate) struct UserFunction {
+  pub param: String,
+  pub body: Vec<Spanned>,
+}
+
+thread_local! {
+  static VARIABLES: RefCell<HashMap<String, Value>> = RefCell::new(HashMap::new());
+  static FUNCTIONS: RefCell<HashMap<String, UserFunction>> = RefCell::new(HashMap::new());
+  static WARNINGS: RefCell<Vec<String>> = const { RefCell::new(Vec::new()) };
+}
+
+/// Returns the value bound to a variable in the current thread's environment.
+pub fn get_variable(name: &str) -> Option<Value> {
+  VARIABLES.with(|vars| vars.borrow().get(name).cloned())
+}
+
+/// Binds a value to a variable in the current thread's environment.
+pub fn set_variable(name: &str, value: Value) {
+  VARIABLES.with(|vars| vars.borrow_mut().insert(name.to_string(), value));
+}
+
+/// Removes a variable from the current thread's environment.
+pub fn remove_variable(name: &str) {
+  VARIABLES.with(|vars| vars.borrow_mut().remove(name));
+}
+
+/// Returns the user function with the given name in the current thread's environment.
+pub(crate) fn get_user_function(name: &str) -> Option<UserFunction> {
+  FUNCTIONS.with(|funcs| funcs.borrow().get(name).cloned())
+}
+
+/// Defines (or redefines) a user function in the current thread's environment.
+pub(crate) fn define_function(name: &str, function: UserFunction) {
+  FUNCTIONS.with(|funcs| funcs.borrow_mut().insert(name.to_string(), function));
+}
+
+/// Records a warning about the evaluation if warnings are enabled.
+pub fn warn(message: String) {
+  if settings().warn {
+    WARNINGS.with(|warnings| warnings.borrow_mut().push(message));
+  }
+}
+
+/// Returns and clears the warnings recorded in the current thread.
+pub fn take_warnings() -> Vec<String> {
+  WARNINGS.with(|warnings| warnings.take())
+}
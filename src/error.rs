@@ -0,0 +1,127 @@
+use crate::number::Width;
+use crate::unit::Unit;
+use std::fmt;
+
+/// An error produced while tokenizing, parsing or evaluating a program.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ClcError {
+  /// Input that isn't a valid token.
+  ///
+  /// The position of this and other errors is the byte offset of the offending input,
+  /// reported as a 1-based column.
+  Lex {
+    slice: String,
+    position: usize,
+  },
+  /// An integer literal that doesn't fit in the given number of bits.
+  IntegerOverflow {
+    slice: String,
+    bits: u32,
+  },
+  /// A bare integer literal that doesn't fit in the default width.
+  WidthOverflow {
+    slice: String,
+    width: Width,
+  },
+  UnknownIdentifier {
+    name: String,
+    position: usize,
+    /// The closest known name, if any is close.
+    suggestion: Option<String>,
+  },
+  /// A function or operator given fewer arguments than it takes.
+  Arity {
+    name: String,
+    expected: usize,
+    got: usize,
+  },
+  /// A function used without any argument (e.g. `sin` on its own).
+  MissingArgument {
+    name: String,
+    position: usize,
+  },
+  /// A `(` or `)` without its matching paren.
+  UnmatchedParen {
+    paren: char,
+    position: usize,
+  },
+  /// A `?` or `:` without its matching half of the conditional.
+  UnmatchedConditional(char),
+  /// A value that has no conversion to the unit.
+  Conversion {
+    from: Unit,
+    to: Unit,
+  },
+  /// A directed conversion (e.g. `c2f`) given a value of the wrong unit.
+  UnsupportedConversion {
+    from: Unit,
+    to: Unit,
+  },
+  /// The operands of a binary operator have incompatible units.
+  IncompatibleUnits {
+    from: Unit,
+    to: Unit,
+  },
+  DivisionByZero,
+  /// The exact result of integer arithmetic that doesn't fit in its width (with `--checked`).
+  Overflow {
+    value: String,
+    width: Width,
+  },
+  /// A definition of a function with the name of a built-in.
+  Redefinition(String),
+  /// An argument outside of the domain of a function.
+  Domain(String),
+  /// Any other malformed input.
+  Syntax(String),
+}
+
+impl fmt::Display for ClcError {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    use ClcError::*;
+    match self {
+      Lex { slice, position } => write!(f, "Unexpected token in input '{}' at column {}", slice, position + 1),
+      IntegerOverflow { slice, bits } => write!(f, "Integer literal '{}' does not fit in {} bits", slice, bits),
+      WidthOverflow { slice, width } => write!(f, "{} does not fit in default width {}", slice, width),
+      Overflow { value, width } => write!(f, "overflow: {} does not fit in {}", value, width),
+      UnknownIdentifier {
+        name,
+        position,
+        suggestion,
+      } => {
+        write!(f, "Unknown identifier '{}' at column {}", name, position + 1)?;
+        match suggestion {
+          Some(suggestion) => write!(f, ", did you mean '{}'?", suggestion),
+          None => Ok(()),
+        }
+      }
+      Arity { name, expected, .. } => match expected {
+        0 => write!(f, "Expected no arguments to {}", name),
+        1 => write!(f, "Expected one argument to {}", name),
+        2 => write!(f, "Expected two arguments to {}", name),
+        3 => write!(f, "Expected three arguments to {}", name),
+        n => write!(f, "Expected {} arguments to {}", n, name),
+      },
+      MissingArgument { name, position } => write!(
+        f,
+        "function '{}' used without an argument at column {}",
+        name,
+        position + 1
+      ),
+      UnmatchedParen { paren: ')', position } => {
+        write!(f, "Encountered ')' without matching '(' at column {}", position + 1)
+      }
+      UnmatchedParen { position, .. } => write!(f, "Encountered '(' without matching ')' at column {}", position + 1),
+      UnmatchedConditional(':') => write!(f, "Encountered ':' without matching '?'"),
+      UnmatchedConditional(_) => write!(f, "Encountered '?' without matching ':'"),
+      Conversion { from, to } => write!(f, "Invalid conversion from {} to {}", from, to),
+      UnsupportedConversion { from, to } => write!(f, "Conversion from {} to {} is not supported", from, to),
+      IncompatibleUnits { from, to } => write!(f, "Unable to convert {} to {}", from, to),
+      DivisionByZero => write!(f, "division by zero"),
+      Redefinition(name) => write!(f, "Cannot redefine built-in '{}'", name),
+      Domain(msg) | Syntax(msg) => write!(f, "{}", msg),
+    }
+  }
+}
+
+impl std::error::Error for ClcError {}
@@ -0,0 +1,45 @@
+/// The version reported by `--version` and `--version-json`.
+pub const VERSION: &str = "1.1";
+
+/// The optional capabilities compiled into this build.
+const FEATURES: &[(&str, bool)] = &[
+  ("alfred", true),                           // alfred script filter output
+  ("clipboard", cfg!(feature = "clipboard")), // copying results with --copy
+  ("serve", cfg!(unix)),                      // evaluating lines from a socket with --serve
+  ("units", true),                            // unit suffixes and conversions
+];
+
+/// Returns the names of all capabilities enabled in this build.
+pub fn features() -> Vec<&'static str> {
+  FEATURES
+    .iter()
+    .filter(|(_, enabled)| *enabled)
+    .map(|(name, _)| *name)
+    .collect()
+}
+
+/// Returns the name, version and enabled features as a JSON object.
+pub fn version_json() -> String {
+  let features = features()
+    .iter()
+    .map(|name| format!(r#""{}""#, name))
+    .collect::<Vec<_>>();
+
+  format!(
+    r#"{{"name":"clc","version":"{}","features":[{}]}}"#,
+    VERSION,
+    features.join(",")
+  )
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_version_json() {
+    let json = version_json();
+    assert!(json.contains(r#""version":"1.1""#));
+    assert!(json.contains(r#""alfred""#));
+  }
+}
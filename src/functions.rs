@@ -1,10 +1,21 @@
+use crate::env::warn;
+use crate::error::ClcError;
+use crate::settings::settings;
 use crate::value::{Number, Unit, Value};
+use num_bigint::BigInt;
+use num_traits::FromPrimitive;
 use phf::phf_map;
+use std::cell::Cell;
+use std::time::{SystemTime, UNIX_EPOCH};
 
 #[derive(Copy, Clone, Debug)]
 pub enum Function {
-  Unary(fn(Value) -> Result<Value, String>),
-  Binary(fn(Value, Value) -> Result<Value, String>),
+  /// A function called with empty parentheses (e.g. `rand()`).
+  Nullary(fn() -> Result<Value, ClcError>),
+  Unary(fn(Value) -> Result<Value, ClcError>),
+  Binary(fn(Value, Value) -> Result<Value, ClcError>),
+  Ternary(fn(Value, Value, Value) -> Result<Value, ClcError>),
+  Variadic(fn(Vec<Value>) -> Result<Value, ClcError>),
 }
 
 /// A macro to define constant values.
@@ -23,7 +34,7 @@ macro_rules! constant {
 ///
 /// ## Examples
 ///
-/// ```
+/// ```ignore
 /// unary!(|v: f64| v.sin())
 /// unary!(|v: Number| f64::from(v).sin())
 /// ```
@@ -53,7 +64,7 @@ macro_rules! unary {
 ///
 /// ## Examples
 ///
-/// ```
+/// ```ignore
 /// binary!(|v1: f64, v2: f64| v1 + v2)
 /// binary!(|v1: Number, v2: Number| v1 + v2)
 /// ```
@@ -77,10 +88,54 @@ macro_rules! binary {
   };
 }
 
+/// A macro to define ternary functions.
+///
+/// The macro wraps a given closure in `Function::Ternary` and works the same as `binary!`
+/// except that it takes three parameters. The unit of the first argument is preserved.
+///
+/// ## Examples
+///
+/// ```ignore
+/// ternary!(|v: Number, hi: u32, lo: u32| v.bits(hi, lo))
+/// ```
+macro_rules! ternary {
+  (|$p1:ident: $t1:ty, $p2:ident: $t2:ty, $p3:ident: $t3:ty| $($rest:tt)*) => {
+    ternary!(_ $t1 $t2 $t3 |$p1: $t1, $p2: $t2, $p3: $t3| $($rest)*)
+  };
+  // internally invoked by the above
+  (_ $t1:tt $t2:tt $t3:tt $callable:expr) => {
+    Function::Ternary(|a: Value, b: Value, c: Value| {
+      let unit = a.unit;
+      let a = <$t1>::from(a.number);
+      let b = <$t2>::from(b.number);
+      let c = <$t3>::from(c.number);
+      Ok(Value::from((Number::from($callable(a, b, c)), unit)))
+    })
+  };
+}
+
 /// A macro to define casting functions.
+///
+/// A cast changes only the type of the number, and the unit of the value is preserved.
 macro_rules! cast {
   ($type:ty) => {
-    Function::Unary(|v: Value| Ok(Value::from((Number::from(<$type>::from(v.number)), Unit::Raw))))
+    Function::Unary(|v: Value| Ok(Value::from((Number::from(<$type>::from(v.number)), v.unit))))
+  };
+}
+
+/// A macro to define checked casts, which give an error instead of losing any of the value.
+macro_rules! try_cast {
+  ($type:ty) => {
+    Function::Unary(|v: Value| {
+      if !exact_integer(&v.number).is_some_and(|n| <$type>::try_from(n).is_ok()) {
+        return Err(ClcError::Domain(format!(
+          "value {} does not fit in {}",
+          v.number,
+          stringify!($type)
+        )));
+      }
+      Ok(Value::from((Number::from(<$type>::from(v.number)), v.unit)))
+    })
   };
 }
 
@@ -89,25 +144,206 @@ macro_rules! convert {
   // convert to a specific unit using any available conversion
   ($unit:expr) => {
     Function::Unary(|v: Value| {
-      v.convert($unit)
-        .ok_or(format!("Invalid conversion from {} to {}", v.unit, $unit))
+      v.convert($unit).ok_or(ClcError::Conversion {
+        from: v.unit,
+        to: $unit,
+      })
     })
   };
   // convert to a specific unit from another given unit (or raw)
   ($from:expr => $to:expr) => {
     Function::Unary(|v: Value| {
       if v.unit == Unit::Raw {
-        Ok(Unit::normalize(v.number, $to))
+        Ok(Value::new(v.number, $to))
       } else if v.unit == $from {
-        v.convert($unit)
-          .ok_or(format!("Invalid conversion from {} to {}", v.unit, $unit))
+        v.convert($to).ok_or(ClcError::Conversion { from: v.unit, to: $to })
       } else {
-        Err(format!("Conversion from {} to {} is not supported", v.unit, $unit))
+        Err(ClcError::UnsupportedConversion { from: v.unit, to: $to })
       }
     })
   };
 }
 
+/// Warns when comparing integers of differing signedness, which uses the left operand's type.
+fn warn_mixed_signedness(a: &Number, b: &Number) {
+  if let (Number::Integer(_, wa), Number::Integer(_, wb)) = (a, b) {
+    if wa.is_signed() != wb.is_signed() {
+      warn(format!("comparing {} with {}; using {} semantics", wa, wb, wa));
+    }
+  }
+}
+
+/// Returns the next representable float towards positive infinity.
+fn next_up(v: f64) -> f64 {
+  if v.is_nan() || v == f64::INFINITY {
+    return v;
+  } else if v == 0.0 {
+    // both +0 and -0 step to the smallest positive subnormal
+    return f64::from_bits(1);
+  }
+
+  let bits = v.to_bits();
+  f64::from_bits(if v > 0.0 { bits + 1 } else { bits - 1 })
+}
+
+/// Returns the dividend and divisor in the type they are divided in, or an error if the divisor
+/// is zero. Two integers are divided in their promoted width, and otherwise the divisor is cast to
+/// the type of the dividend, unless that would make it zero (e.g. `1 / 0.5`) and both are divided
+/// as floats instead.
+fn division(a: &Number, b: &Number) -> Result<(Number, Number), ClcError> {
+  if !bool::from(b.clone()) {
+    return Err(ClcError::DivisionByZero);
+  }
+  match (a, b) {
+    (Number::Integer(..), Number::Integer(..)) => Ok((a.clone(), b.clone())),
+    _ if bool::from(b.to_type_of(a)) => Ok((a.clone(), b.to_type_of(a))),
+    _ => Ok((a.to_float(), b.to_float())),
+  }
+}
+
+/// Returns the wrapped result of integer arithmetic, or an error if `--checked` is set and the
+/// exact result computed by `exact` doesn't fit in its width. A float operand is truncated to
+/// the integer's width first, which is an error if it doesn't fit either.
+fn checked(a: &Number, b: &Number, result: Number, exact: fn(BigInt, BigInt) -> BigInt) -> Result<Number, ClcError> {
+  match result {
+    Number::Integer(_, width) if settings().checked && a.is_integer() && !b.is_big() => {
+      let b_exact = BigInt::from(b.clone());
+      if b.is_float() && b_exact != BigInt::from(b.to_type_of(a)) {
+        return Err(ClcError::Overflow {
+          value: b_exact.to_string(),
+          width,
+        });
+      }
+      let exact = exact(BigInt::from(a.clone()), b_exact);
+      if exact == BigInt::from(result.clone()) {
+        Ok(result)
+      } else {
+        Err(ClcError::Overflow {
+          value: exact.to_string(),
+          width,
+        })
+      }
+    }
+    _ => Ok(result),
+  }
+}
+
+/// Returns an error if a big integer would be raised or shifted left by a count that doesn't
+/// fit in a `u32`, which would otherwise be truncated.
+fn big_count(kind: &str, a: &Number, b: &Number) -> Result<(), ClcError> {
+  if a.is_big() && u32::try_from(BigInt::from(b.clone())).is_err() {
+    return Err(ClcError::Domain(format!(
+      "{} {} is out of range for a big integer",
+      kind, b
+    )));
+  }
+  Ok(())
+}
+
+fn multiply(a: Value, b: Value) -> Result<Value, ClcError> {
+  let product = checked(&a.number, &b.number, a.number.clone() * b.number.clone(), |x, y| x * y)?;
+  Ok(Value::from((product, a.unit)))
+}
+
+/// Returns the remainder of truncated division, which has the sign of the dividend.
+fn remainder(a: Value, b: Value) -> Result<Value, ClcError> {
+  let (dividend, divisor) = division(&a.number, &b.number)?;
+  Ok(Value::from((dividend % divisor, a.unit)))
+}
+
+/// Returns the remainder of Euclidean division, which is never negative.
+fn euclidean_remainder(a: Value, b: Value) -> Result<Value, ClcError> {
+  let (dividend, divisor) = division(&a.number, &b.number)?;
+  Ok(Value::from((dividend.rem_euclid(&divisor), a.unit)))
+}
+
+/// Returns the integer a number is exactly equal to, or `None` for a float with a fraction.
+fn exact_integer(number: &Number) -> Option<BigInt> {
+  match number {
+    Number::Float(v) => BigInt::from_f64(*v).filter(|_| v.fract() == 0.0),
+    _ => Some(BigInt::from(number.clone())),
+  }
+}
+
+/// Checks that a number is an ASCII code point.
+fn ascii(number: Number) -> Result<Number, ClcError> {
+  match number.to_ascii() {
+    Some(_) => Ok(number),
+    None => Err(ClcError::Domain(format!("{} is not an ASCII code point", number))),
+  }
+}
+
+/// Reinterprets the bits of an integer as the other signedness of its width.
+fn reinterpret(name: &str, v: Value, f: fn(&Number) -> Number) -> Result<Value, ClcError> {
+  if let Number::Float(_) = v.number {
+    return Err(ClcError::Domain(format!("{} of a float", name)));
+  }
+  Ok(Value::from((f(&v.number), v.unit)))
+}
+
+/// Applies `nCr` or `nPr` to integer operands, erroring if the result doesn't fit in the width of `n`.
+fn combinatorics(name: &str, n: Value, k: Value, f: fn(&Number, &Number) -> Option<Number>) -> Result<Value, ClcError> {
+  if n.number.is_float() || k.number.is_float() {
+    return Err(ClcError::Domain(format!("{} of a non-integer", name)));
+  }
+  if n.number < Number::from(0i64) || k.number < Number::from(0i64) {
+    return Err(ClcError::Domain(format!("{} of a negative number", name)));
+  }
+  let result = f(&n.number, &k.number).ok_or_else(|| ClcError::Overflow {
+    value: format!("{}({}, {})", name, n.number, k.number),
+    width: n.number.width(),
+  })?;
+  Ok(Value::from((result, n.unit)))
+}
+
+thread_local! {
+  static RANDOM_STATE: Cell<Option<u64>> = const { Cell::new(None) };
+}
+
+/// Returns the next number of a splitmix64 generator, which is seeded with the `seed` setting or
+/// else the system clock on first use.
+fn next_random() -> u64 {
+  RANDOM_STATE.with(|state| {
+    let seed = state.get().unwrap_or_else(|| {
+      let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default();
+      settings().seed.unwrap_or(now.as_nanos() as u64)
+    });
+    let next = seed.wrapping_add(0x9E3779B97F4A7C15);
+    state.set(Some(next));
+
+    let mut z = next;
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58476D1CE4E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D049BB133111EB);
+    z ^ (z >> 31)
+  })
+}
+
+/// Returns the geometric mean, which requires all values to be non-negative.
+fn geomean(values: Vec<Value>) -> Result<Value, ClcError> {
+  let values = values.into_iter().map(|v| f64::from(v.number)).collect::<Vec<_>>();
+  if values.iter().any(|v| *v < 0.0) {
+    return Err(ClcError::Domain("geomean of a negative number".to_string()));
+  }
+  // the mean of the logarithms avoids overflowing the product
+  let mean = values.iter().map(|v| v.ln()).sum::<f64>() / values.len() as f64;
+  Ok(Value::new_float(mean.exp()))
+}
+
+/// Returns the harmonic mean, which requires all values to be positive.
+fn harmmean(values: Vec<Value>) -> Result<Value, ClcError> {
+  let values = values.into_iter().map(|v| f64::from(v.number)).collect::<Vec<_>>();
+  if values.iter().any(|v| *v <= 0.0) {
+    return Err(ClcError::Domain("harmmean of a non-positive number".to_string()));
+  }
+  let sum = values.iter().map(|v| 1.0 / v).sum::<f64>();
+  Ok(Value::new_float(values.len() as f64 / sum))
+}
+
+/// Returns the next representable float towards negative infinity.
+fn next_down(v: f64) -> f64 {
+  -next_up(-v)
+}
+
 //
 
 const CONST_TABLE: phf::Map<&'static str, fn() -> Value> = phf_map! {
@@ -119,6 +355,8 @@ const CONST_TABLE: phf::Map<&'static str, fn() -> Value> = phf_map! {
 
   "F64_MIN" => constant!(f64::MIN),
   "F64_MAX" => constant!(f64::MAX),
+  "U128_MIN" => constant!(u128::MIN),
+  "U128_MAX" => constant!(u128::MAX),
   "U64_MIN" => constant!(u64::MIN),
   "U64_MAX" => constant!(u64::MAX),
   "U32_MIN" => constant!(u32::MIN),
@@ -127,6 +365,8 @@ const CONST_TABLE: phf::Map<&'static str, fn() -> Value> = phf_map! {
   "U16_MAX" => constant!(u16::MAX),
   "U8_MIN" => constant!(u8::MIN),
   "U8_MAX" => constant!(u8::MAX),
+  "I128_MIN" => constant!(i128::MIN),
+  "I128_MAX" => constant!(i128::MAX),
   "I64_MIN" => constant!(i64::MIN),
   "I64_MAX" => constant!(i64::MAX),
   "I32_MIN" => constant!(i32::MIN),
@@ -143,29 +383,75 @@ const FUNC_TABLE: phf::Map<&'static str, Function> = phf_map! {
   "-u" => unary!(|v: Number| -v),
   "!u" => unary!(|v: bool| !v),
   "~u" => unary!(|v: Number| !v),
+  "%p" => unary!(|v: f64| v / 100.0),
 
-  "+" => binary!(|a: Number, b: Number| a + b),
-  "-" => binary!(|a: Number, b: Number| a - b),
-  "*" => binary!(|a: Number, b: Number| a * b),
-  "/" => binary!(|a: Number, b: Number| a / b),
-  "%" => binary!(|a: Number, b: Number| a % b),
+  "+" => Function::Binary(|a, b| {
+    let sum = checked(&a.number, &b.number, a.number.clone() + b.number.clone(), |x, y| x + y)?;
+    Ok(Value::from((sum, a.unit)))
+  }),
+  "-" => Function::Binary(|a, b| {
+    let difference = if settings().signed_sub && a.number.is_unsigned() && a.number < b.number {
+      a.number.to_signed() - b.number.to_signed()
+    } else {
+      a.number.clone() - b.number.clone()
+    };
+    Ok(Value::from((checked(&a.number, &b.number, difference, |x, y| x - y)?, a.unit)))
+  }),
+  "*" => Function::Binary(multiply),
+  // implied multiplication (e.g. `2PI`) is the same as `*`
+  "*i" => Function::Binary(multiply),
+  "/" => Function::Binary(|a, b| {
+    let (dividend, divisor) = division(&a.number, &b.number)?;
+    Ok(Value::from((dividend / divisor, a.unit)))
+  }),
+  "%" => Function::Binary(remainder),
+  "**" => Function::Binary(|a, b| {
+    big_count("exponent", &a.number, &b.number)?;
+    let pow = a.number.pow(&b.number);
+    Ok(Value::from((pow.ok_or_else(|| ClcError::Domain("negative exponent requires float base".to_string()))?, a.unit)))
+  }),
 
   "&" => binary!(|a: Number, b: Number| a & b),
   "|" => binary!(|a: Number, b: Number| a | b),
   "^" => binary!(|a: Number, b: Number| a ^ b),
 
-  "<<" => binary!(|a: Number, b: Number| a << b),
+  "<<" => Function::Binary(|a, b| {
+    big_count("shift count", &a.number, &b.number)?;
+    Ok(Value::from((a.number << b.number, a.unit)))
+  }),
   ">>" => binary!(|a: Number, b: Number| a >> b),
 
-  "<" => binary!(|a: Number, b: Number| a < b),
-  ">" => binary!(|a: Number, b: Number| a > b),
-  ">=" => binary!(|a: Number, b: Number| a >= b),
-  "<=" => binary!(|a: Number, b: Number| a <= b),
-  "==" => binary!(|a: Number, b: Number| a == b),
-  "!=" => binary!(|a: Number, b: Number| a != b),
+  "<" => binary!(|a: Number, b: Number| {
+    warn_mixed_signedness(&a, &b);
+    a < b
+  }),
+  ">" => binary!(|a: Number, b: Number| {
+    warn_mixed_signedness(&a, &b);
+    a > b
+  }),
+  ">=" => binary!(|a: Number, b: Number| {
+    warn_mixed_signedness(&a, &b);
+    a >= b
+  }),
+  "<=" => binary!(|a: Number, b: Number| {
+    warn_mixed_signedness(&a, &b);
+    a <= b
+  }),
+  "==" => binary!(|a: Number, b: Number| {
+    warn_mixed_signedness(&a, &b);
+    a == b
+  }),
+  "!=" => binary!(|a: Number, b: Number| {
+    warn_mixed_signedness(&a, &b);
+    a != b
+  }),
 
   "&&" => binary!(|a: bool, b: bool| a && b),
   "||" => binary!(|a: bool, b: bool| a || b),
+  "^^" => binary!(|a: bool, b: bool| a ^ b),
+
+  // the parser only evaluates the branch that is taken, so this is used for its arity
+  "?:" => Function::Ternary(|c, a, b| Ok(if bool::from(c) { a } else { b })),
 
   // functions
   "abs" => unary!(|v: Number| v.abs()),
@@ -175,27 +461,141 @@ const FUNC_TABLE: phf::Map<&'static str, Function> = phf_map! {
   "asin" => unary!(|v: f64| v.asin()),
   "acos" => unary!(|v: f64| v.asin()),
   "atan" => unary!(|v: f64| v.asin()),
+  "sinh" => unary!(|v: f64| v.sinh()),
+  "cosh" => unary!(|v: f64| v.cosh()),
+  "tanh" => unary!(|v: f64| v.tanh()),
+  "asinh" => unary!(|v: f64| v.asinh()),
+  "acosh" => unary!(|v: f64| v.acosh()),
+  "atanh" => unary!(|v: f64| v.atanh()),
   "floor" => unary!(|v: f64| v.floor()),
   "ceil" => unary!(|v: f64| v.ceil()),
   "round" => unary!(|v: f64| v.round()),
+  "roundn" => binary!(|v: f64, n: i32| {
+    let scale = 10f64.powi(n);
+    (v * scale).round() / scale
+  }),
   "sqrt" => unary!(|v: f64| v.sqrt()),
+  "cbrt" => unary!(|v: f64| v.cbrt()),
+  "trunc" => unary!(|v: f64| v.trunc()),
+  "fract" => unary!(|v: f64| v.fract()),
+  // unlike `f64::signum`, the sign of zero is zero
+  "sign" => unary!(|v: f64| if v == 0.0 { 0.0 } else { v.signum() }),
   "exp" => unary!(|v: f64| v.exp()),
   "ln" => unary!(|v: f64| v.ln()),
   "log2" => unary!(|v: f64| v.log2()),
   "log10" => unary!(|v: f64| v.log10()),
+  "clamp" => ternary!(|x: Number, lo: Number, hi: Number| {
+    // bounds take the type of `x`, and a degenerate range yields the lower bound
+    let (lo, hi) = (lo.to_type_of(&x), hi.to_type_of(&x));
+    if lo > hi { lo } else { x.clamp(lo, hi) }
+  }),
+  // zero and NaN are empty, so the default is used in their place
+  "coalesce" => Function::Binary(|a, b| {
+    let empty = !bool::from(a.number.clone()) || f64::from(a.number.clone()).is_nan();
+    Ok(if empty { b } else { a })
+  }),
+  "divor" => Function::Ternary(|a, b, default| match division(&a.number, &b.number) {
+    Ok((dividend, divisor)) => Ok(Value::from((dividend / divisor, a.unit))),
+    Err(_) => Ok(default),
+  }),
+  "rem_euclid" => Function::Binary(euclidean_remainder),
+  // explicit truncated and Euclidean remainders
+  "rem" => Function::Binary(remainder),
+  "mod" => Function::Binary(euclidean_remainder),
+  "fact" => Function::Unary(|v| {
+    if v.number < Number::from(0i64) {
+      return Err(ClcError::Domain("factorial of a negative number".to_string()));
+    }
+    Ok(Value::from((v.number.factorial(), v.unit)))
+  }),
+  "nCr" => Function::Binary(|n, k| combinatorics("nCr", n, k, Number::combinations)),
+  "nPr" => Function::Binary(|n, k| combinatorics("nPr", n, k, Number::permutations)),
+  "issquare" => unary!(|v: Number| v.is_square()),
+  "isqrt" => unary!(|v: Number| v.isqrt()),
+  "ilog2" => Function::Unary(|v| {
+    let log = v.number.ilog2();
+    Ok(Value::from((log.ok_or_else(|| ClcError::Domain("ilog2 of a non-positive number".to_string()))?, v.unit)))
+  }),
+  "ispow" => binary!(|n: Number, k: u32| n.is_power(k)),
+  "rand" => Function::Nullary(|| Ok(Value::new_float((next_random() >> 11) as f64 / (1u64 << 53) as f64))),
+  "randint" => Function::Binary(|lo, hi| {
+    let (min, max) = (BigInt::from(lo.number.clone()), BigInt::from(hi.number));
+    if min > max {
+      return Err(ClcError::Domain("randint of an empty range".to_string()));
+    }
+    let random = (u128::from(next_random()) << 64) | u128::from(next_random());
+    let n = min.clone() + BigInt::from(random) % (max - min + 1);
+    Ok(Value::from((Number::Big(n).to_type_of(&lo.number), lo.unit)))
+  }),
+  "geomean" => Function::Variadic(geomean),
+  "harmmean" => Function::Variadic(harmmean),
+  "digitsum" => unary!(|v: Number| v.digit_sum()),
+  "digitalroot" => unary!(|v: Number| v.digital_root()),
+  "ndigits" => unary!(|v: Number| v.digit_count()),
+  "nextup" => unary!(|v: f64| next_up(v)),
+  "nextdown" => unary!(|v: f64| next_down(v)),
+  "isnan" => unary!(|v: Value| Number::from(v.number.is_float() && f64::from(v.number).is_nan())),
+  "isinf" => unary!(|v: Value| Number::from(v.number.is_float() && f64::from(v.number).is_infinite())),
+  "isfinite" => unary!(|v: Value| Number::from(v.number.is_integer() || f64::from(v.number).is_finite())),
   "deg" => unary!(|v: f64| v / (std::f64::consts::FRAC_1_PI * 180.0)),
   "rad" => unary!(|v: f64| v * (std::f64::consts::FRAC_1_PI * 180.0)),
+  "turns" => unary!(|v: f64| v * std::f64::consts::TAU),
+  "torad" => unary!(|v: f64| v.to_radians()),
+  "todeg" => unary!(|v: f64| v.to_degrees()),
+
+  // bit manipulation
+  "bits" => ternary!(|v: Number, hi: u32, lo: u32| v.bits(hi, lo)),
+  "setbit" => binary!(|v: Number, n: u32| v.set_bit(n)),
+  "clrbit" => binary!(|v: Number, n: u32| v.clear_bit(n)),
+  "togglebit" => binary!(|v: Number, n: u32| v.toggle_bit(n)),
+  "xorbytes" => unary!(|v: Number| v.xor_bytes()),
+  "bitrevn" => Function::Binary(|a, b| {
+    let (n, width) = (u32::from(b.number), a.number.width());
+    if n > width.bits() {
+      return Err(ClcError::Domain(format!("cannot reverse {} bits of a {}", n, width)));
+    }
+    Ok(Value::from((a.number.bitrevn(n), a.unit)))
+  }),
+  "bitrev" => Function::Unary(|v| {
+    let kind = if v.number.is_big() { "big integer" } else { "float" };
+    let reversed = v.number.reverse_bits().ok_or_else(|| ClcError::Domain(format!("bitrev of a {}", kind)))?;
+    Ok(Value::from((reversed, v.unit)))
+  }),
+  "gray" => unary!(|v: Number| v.gray()),
+  "ungray" => unary!(|v: Number| v.ungray()),
+  "lsb" => unary!(|v: Number| v.lsb()),
+  "blsr" => unary!(|v: Number| v.blsr()),
+  "hamming" => Function::Binary(|a, b| {
+    let distance = a.number.hamming(&b.number);
+    Ok(Value::from(distance.ok_or_else(|| ClcError::Domain("hamming distance of a float".to_string()))?))
+  }),
+  "bits_for" => unary!(|v: u64| if v <= 1 { 0 } else { (u64::BITS - (v - 1).leading_zeros()) as u64 }),
 
   // casting
+  "u128" => cast!(u128),
   "u64" => cast!(u64),
   "u32" => cast!(u32),
   "u16" => cast!(u16),
   "u8" => cast!(u8),
+  "i128" => cast!(i128),
   "i64" => cast!(i64),
   "i32" => cast!(i32),
   "i16" => cast!(i16),
   "i8" => cast!(i8),
   "f64" => cast!(f64),
+  "f32" => cast!(f32),
+  "tryu128" => try_cast!(u128),
+  "tryu64" => try_cast!(u64),
+  "tryu32" => try_cast!(u32),
+  "tryu16" => try_cast!(u16),
+  "tryu8" => try_cast!(u8),
+  "tryi128" => try_cast!(i128),
+  "tryi64" => try_cast!(i64),
+  "tryi32" => try_cast!(i32),
+  "tryi16" => try_cast!(i16),
+  "tryi8" => try_cast!(i8),
+  "signed" => Function::Unary(|v| reinterpret("signed", v, Number::to_signed)),
+  "unsigned" => Function::Unary(|v| reinterpret("unsigned", v, Number::to_unsigned)),
 
   // unit conversion
   "bytes" => convert!(Unit::Byte),
@@ -204,10 +604,34 @@ const FUNC_TABLE: phf::Map<&'static str, Function> = phf_map! {
   "gigabyte" => convert!(Unit::Gigabyte),
   "terabyte" => convert!(Unit::Terabyte),
   "petabyte" => convert!(Unit::Petabyte),
+  "si_kilobyte" => convert!(Unit::SiKilobyte),
+  "si_megabyte" => convert!(Unit::SiMegabyte),
+  "si_gigabyte" => convert!(Unit::SiGigabyte),
+  "si_terabyte" => convert!(Unit::SiTerabyte),
+  "si_petabyte" => convert!(Unit::SiPetabyte),
 
   "celsius" => convert!(Unit::Celsius),
   "fahrenheit" => convert!(Unit::Fahrenheit),
   "kelvin" => convert!(Unit::Kelvin),
+  "c2f" => convert!(Unit::Celsius => Unit::Fahrenheit),
+  "f2c" => convert!(Unit::Fahrenheit => Unit::Celsius),
+
+  "seconds" => convert!(Unit::Second),
+
+  "chr" => Function::Unary(|v| Ok(Value::from((ascii(v.number)?, Unit::Char)))),
+  // the code point of a character, which is written as a literal (e.g. `ord('A')`)
+  "ord" => Function::Unary(|v| Ok(Value::from(ascii(v.number)?))),
+  "ipv4" => Function::Unary(|v| match v.number.to_ipv4() {
+    Some(_) => Ok(Value::from((v.number, Unit::Ipv4))),
+    None => Err(ClcError::Domain(format!("{} is not an IPv4 address", v.number))),
+  }),
+
+  "bytes_per_sec" => convert!(Unit::BytePerSecond),
+  "kilobyte_per_sec" => convert!(Unit::KilobytePerSecond),
+  "megabyte_per_sec" => convert!(Unit::MegabytePerSecond),
+  "gigabyte_per_sec" => convert!(Unit::GigabytePerSecond),
+  "terabyte_per_sec" => convert!(Unit::TerabytePerSecond),
+  "petabyte_per_sec" => convert!(Unit::PetabytePerSecond),
 };
 
 const ALIAS_TABLE: phf::Map<&'static str, &'static str> = phf_map! {
@@ -220,17 +644,684 @@ const ALIAS_TABLE: phf::Map<&'static str, &'static str> = phf_map! {
   "tempC" => "celsius",
   "tempF" => "fahrenheit",
   "tempK" => "kelvin",
+
+  "signum" => "sign",
 };
 
 pub fn get_constant(name: &str) -> Option<Value> {
   CONST_TABLE.get(name).map(|f| f())
 }
 
+/// Returns whether the named function casts to a type (e.g. `u8`).
+pub fn is_cast(name: &str) -> bool {
+  matches!(
+    name,
+    "u128" | "u64" | "u32" | "u16" | "u8" | "i128" | "i64" | "i32" | "i16" | "i8" | "f64" | "f32"
+  )
+}
+
+/// Casts a value to the named type, or returns `None` if the name isn't a type.
+pub fn cast_to(name: &str, value: Value) -> Option<Value> {
+  match FUNC_TABLE.get(name) {
+    Some(Function::Unary(cast)) if is_cast(name) => cast(value).ok(),
+    _ => None,
+  }
+}
+
+/// Returns the unit converted to by the named conversion function (or alias).
+pub fn get_unit(name: &str) -> Option<Unit> {
+  Unit::from_name(ALIAS_TABLE.get(name).copied().unwrap_or(name))
+}
+
+/// Returns the sorted names of all functions grouped into operators, casts, conversions and
+/// math, followed by the aliases, one group per line.
+pub fn list_functions() -> String {
+  let mut groups: [(&str, Vec<String>); 4] = [
+    ("operators", vec![]),
+    ("casts", vec![]),
+    ("conversions", vec![]),
+    ("math", vec![]),
+  ];
+
+  for name in FUNC_TABLE.keys() {
+    let group = if !name.starts_with(|c: char| c.is_alphabetic()) {
+      // internal variants of operators (e.g. `-u` for unary minus) are not listed
+      if name.ends_with(|c: char| c.is_alphabetic()) {
+        continue;
+      }
+      0
+    } else if is_cast(name) {
+      1
+    } else if get_unit(name).is_some() || matches!(*name, "c2f" | "f2c") {
+      2
+    } else {
+      3
+    };
+    groups[group].1.push(name.to_string());
+  }
+
+  let mut aliases = ALIAS_TABLE
+    .entries()
+    .map(|(alias, name)| format!("{} ({})", alias, name))
+    .collect::<Vec<_>>();
+  aliases.sort();
+
+  groups
+    .iter_mut()
+    .map(|(group, names)| {
+      names.sort();
+      format!("{}: {}", group, names.join(" "))
+    })
+    .chain([format!("aliases: {}", aliases.join(" "))])
+    .collect::<Vec<_>>()
+    .join("\n")
+}
+
+/// Returns the sorted names of all constants, one per line.
+pub fn list_constants() -> String {
+  let mut names = CONST_TABLE.keys().copied().collect::<Vec<_>>();
+  names.sort();
+  names.join("\n")
+}
+
+/// Returns the Levenshtein (edit) distance between two strings.
+fn levenshtein(a: &str, b: &str) -> usize {
+  let b = b.chars().collect::<Vec<_>>();
+  let mut prev = (0..=b.len()).collect::<Vec<_>>();
+  for (i, ca) in a.chars().enumerate() {
+    let mut row = vec![i + 1];
+    for (j, cb) in b.iter().enumerate() {
+      row.push((prev[j] + (ca != *cb) as usize).min(prev[j + 1] + 1).min(row[j] + 1));
+    }
+    prev = row;
+  }
+  prev[b.len()]
+}
+
+/// Returns the known constant, function or alias closest to an unknown name, if any is close.
+///
+/// Names are compared ignoring case, and among equally close names the ones sharing the
+/// longest prefix are preferred (e.g. `sinn` suggests `sin` over `sign`).
+pub fn closest_name(name: &str) -> Option<&'static str> {
+  let name = name.to_lowercase();
+  CONST_TABLE
+    .keys()
+    .chain(FUNC_TABLE.keys())
+    .chain(ALIAS_TABLE.keys())
+    .filter(|key| key.starts_with(|c: char| c.is_alphabetic() || c == '_'))
+    .map(|key| {
+      let lower = key.to_lowercase();
+      let prefix = lower.chars().zip(name.chars()).take_while(|(a, b)| a == b).count();
+      (levenshtein(&name, &lower), std::cmp::Reverse(prefix), *key)
+    })
+    .filter(|(distance, ..)| *distance <= 2)
+    .min()
+    .map(|(.., key)| key)
+}
+
 pub fn get_function(name: &str) -> Option<Function> {
-  FUNC_TABLE.get(name).map(|f| f.clone()).or_else(|| {
-    ALIAS_TABLE
-      .get(name)
-      .and_then(|alias| FUNC_TABLE.get(alias))
-      .map(|f| f.clone())
-  })
+  FUNC_TABLE
+    .get(name)
+    .copied()
+    .or_else(|| ALIAS_TABLE.get(name).and_then(|alias| FUNC_TABLE.get(alias)).copied())
+}
+
+#[cfg(test)]
+mod tests {
+  use super::{closest_name, list_constants, list_functions, next_down, next_up};
+  use crate::env::take_warnings;
+  use crate::lexer::tokenize;
+  use crate::parser::parse;
+  use crate::settings::{set_settings, Settings};
+  use crate::value::{Number, Unit, Value, Width};
+  use test_case::test_case;
+
+  #[test_case("bits(0xFF, 3, 0)" => Ok(Value::new_integer(0x0F, Width::U64)))]
+  #[test_case("bits(0xABCD, 15, 8)" => Ok(Value::new_integer(0xAB, Width::U64)))]
+  #[test_case("bits(u8(0xFF), 63, 4)" => Ok(Value::new_integer(0x0F, Width::U8)))]
+  #[test_case("setbit(0, 4)" => Ok(Value::new_integer(16, Width::U64)))]
+  #[test_case("setbit(u8(0), 8)" => Ok(Value::new_integer(0, Width::U8)))]
+  #[test_case("clrbit(0xFF, 0)" => Ok(Value::new_integer(0xFE, Width::U64)))]
+  #[test_case("togglebit(0b1010, 1)" => Ok(Value::new_integer(0b1000, Width::U64)))]
+  #[test_case("togglebit(0b1010, 0)" => Ok(Value::new_integer(0b1011, Width::U64)))]
+  #[test_case("xorbytes(0x1234u16)" => Ok(Value::new_integer(0x26, Width::U8)))]
+  #[test_case("xorbytes(0xFFu8)" => Ok(Value::new_integer(0xFF, Width::U8)))]
+  #[test_case("xorbytes(0x01020304)" => Ok(Value::new_integer(0x04, Width::U8)))]
+  #[test_case("xorbytes(i16(-1))" => Ok(Value::new_integer(0, Width::U8)))]
+  #[test_case("gray(0)" => Ok(Value::new_integer(0, Width::U64)))]
+  #[test_case("gray(1)" => Ok(Value::new_integer(1, Width::U64)))]
+  #[test_case("gray(2)" => Ok(Value::new_integer(3, Width::U64)))]
+  #[test_case("gray(3)" => Ok(Value::new_integer(2, Width::U64)))]
+  #[test_case("gray(u8(0xFF))" => Ok(Value::new_integer(0x80, Width::U8)))]
+  #[test_case("gray(i8(-1))" => Ok(Value::new_integer(0x80, Width::I8)); "signed within width")]
+  #[test_case("ungray(3)" => Ok(Value::new_integer(2, Width::U64)))]
+  #[test_case("ungray(u8(0x80))" => Ok(Value::new_integer(0xFF, Width::U8)))]
+  #[test_case("lsb(0b1100)" => Ok(Value::new_integer(0b0100, Width::U64)))]
+  #[test_case("lsb(0)" => Ok(Value::new_integer(0, Width::U64)))]
+  #[test_case("lsb(i8(-128))" => Ok(Value::new_integer(0x80, Width::I8)); "negation within width")]
+  #[test_case("blsr(0b1100)" => Ok(Value::new_integer(0b1000, Width::U64)))]
+  #[test_case("blsr(0)" => Ok(Value::new_integer(0, Width::U64)))]
+  #[test_case("blsr(u8(0x80))" => Ok(Value::new_integer(0, Width::U8)))]
+  #[test_case("bitrev(u8(0b00000001))" => Ok(Value::new_integer(0b10000000, Width::U8)))]
+  #[test_case("bitrev(0b11010000u8)" => Ok(Value::new_integer(0b00001011, Width::U8)))]
+  #[test_case("bitrev(0b10000001u8)" => Ok(Value::new_integer(0b10000001, Width::U8)); "palindrome")]
+  #[test_case("bitrev(1u16)" => Ok(Value::new_integer(0x8000, Width::U16)))]
+  #[test_case("bitrev(0x00018001u32)" => Ok(Value::new_integer(0x8001_8000, Width::U32)))]
+  #[test_case("bitrev(0x12)" => Ok(Value::new_integer(0x4800_0000_0000_0000, Width::U64)))]
+  #[test_case("bitrev(1u128)" => Ok(Value::new_integer(1 << 127, Width::U128)))]
+  #[test_case("bitrev(1i8)" => Ok(Value::new_integer(i8::MIN as u128, Width::I8)))]
+  #[test_case("bitrev(-2i16)" => Ok(Value::new_integer(0x7FFF, Width::I16)))]
+  #[test_case("bitrev(3i32)" => Ok(Value::new_integer(0xC000_0000u32 as i32 as u128, Width::I32)))]
+  #[test_case("bitrev(1i64)" => Ok(Value::new_integer(i64::MIN as u128, Width::I64)))]
+  #[test_case("bitrev(-1i128)" => Ok(Value::new_integer(u128::MAX, Width::I128)); "signed palindrome")]
+  #[test_case("bitrev(1.0)" => Err("bitrev of a float".to_string()))]
+  #[test_case("bitrevn(0b0001, 4)" => Ok(Value::new_integer(0b1000, Width::U64)))]
+  #[test_case("bitrevn(0b00000001, 4)" => Ok(Value::new_integer(0b00001000, Width::U64)))]
+  #[test_case("bitrevn(0xF0u8, 4)" => Ok(Value::new_integer(0xF0, Width::U8)); "high bits kept")]
+  #[test_case("bitrevn(0b11010110u8, 3)" => Ok(Value::new_integer(0b1101_0011, Width::U8)))]
+  #[test_case("bitrevn(1u8, 8)" => Ok(Value::new_integer(0x80, Width::U8)); "full width")]
+  #[test_case("bitrevn(5, 0)" => Ok(Value::new_integer(5, Width::U64)); "no bits")]
+  #[test_case("bitrevn(1u8, 9)" => Err("cannot reverse 9 bits of a u8".to_string()))]
+  #[test_case("hamming(0b1010, 0b0110)" => Ok(Value::new_integer(2, Width::U64)))]
+  #[test_case("hamming(0xFF, 0x00)" => Ok(Value::new_integer(8, Width::U64)))]
+  #[test_case("hamming(i8(-1), u16(0))" => Ok(Value::new_integer(16, Width::U64)); "sign extended to wider width")]
+  #[test_case("hamming(u8(0xFF), 0)" => Ok(Value::new_integer(8, Width::U64)))]
+  #[test_case("hamming(1.5, 1)" => Err("hamming distance of a float".to_string()))]
+  #[test_case("bits(1, 2)" => Err("Expected three arguments to bits".to_string()))]
+  fn test_bit_functions(input: &str) -> Result<Value, String> {
+    tokenize(input).and_then(parse).map_err(|err| err.to_string())
+  }
+
+  #[test_case("bits_for(0)" => Ok(Value::new_integer(0, Width::U64)))]
+  #[test_case("bits_for(1)" => Ok(Value::new_integer(0, Width::U64)))]
+  #[test_case("bits_for(2)" => Ok(Value::new_integer(1, Width::U64)))]
+  #[test_case("bits_for(3)" => Ok(Value::new_integer(2, Width::U64)))]
+  #[test_case("bits_for(256)" => Ok(Value::new_integer(8, Width::U64)))]
+  #[test_case("bits_for(257)" => Ok(Value::new_integer(9, Width::U64)))]
+  #[test_case("bits_for(U64_MAX)" => Ok(Value::new_integer(64, Width::U64)))]
+  fn test_bits_for(input: &str) -> Result<Value, String> {
+    tokenize(input).and_then(parse).map_err(|err| err.to_string())
+  }
+
+  #[test_case("isnan(NAN)" => Ok(Value::new_integer(1, Width::U8)))]
+  #[test_case("isnan(1.0)" => Ok(Value::new_integer(0, Width::U8)))]
+  #[test_case("isnan(1)" => Ok(Value::new_integer(0, Width::U8)))]
+  #[test_case("isinf(INF)" => Ok(Value::new_integer(1, Width::U8)))]
+  #[test_case("isinf(NEG_INF)" => Ok(Value::new_integer(1, Width::U8)))]
+  #[test_case("isinf(U64_MAX)" => Ok(Value::new_integer(0, Width::U8)))]
+  #[test_case("isfinite(1.0)" => Ok(Value::new_integer(1, Width::U8)))]
+  #[test_case("isfinite(INF)" => Ok(Value::new_integer(0, Width::U8)))]
+  #[test_case("isfinite(NAN)" => Ok(Value::new_integer(0, Width::U8)))]
+  #[test_case("isfinite(5)" => Ok(Value::new_integer(1, Width::U8)))]
+  fn test_float_predicates(input: &str) -> Result<Value, String> {
+    tokenize(input).and_then(parse).map_err(|err| err.to_string())
+  }
+
+  #[test_case("digitsum(12345)" => Ok(Value::new_integer(15, Width::U64)))]
+  #[test_case("digitsum(0)" => Ok(Value::new_integer(0, Width::U64)))]
+  #[test_case("digitsum(i16(-123))" => Ok(Value::new_integer(6, Width::I16)))]
+  #[test_case("digitsum(i8(-128))" => Ok(Value::new_integer(11, Width::I8)))]
+  #[test_case("ndigits(0)" => Ok(Value::new_integer(1, Width::U64)))]
+  #[test_case("ndigits(7)" => Ok(Value::new_integer(1, Width::U64)))]
+  #[test_case("ndigits(999)" => Ok(Value::new_integer(3, Width::U64)))]
+  #[test_case("ndigits(1000)" => Ok(Value::new_integer(4, Width::U64)))]
+  #[test_case("ndigits(i8(-12))" => Ok(Value::new_integer(2, Width::I8)))]
+  #[test_case("ndigits(i8(-128))" => Ok(Value::new_integer(3, Width::I8)))]
+  #[test_case("ndigits(U64_MAX)" => Ok(Value::new_integer(20, Width::U64)))]
+  #[test_case("digitalroot(12345)" => Ok(Value::new_integer(6, Width::U64)))]
+  #[test_case("digitalroot(9999)" => Ok(Value::new_integer(9, Width::U64)))]
+  #[test_case("digitalroot(0)" => Ok(Value::new_integer(0, Width::U64)))]
+  #[test_case("digitalroot(i32(-12345))" => Ok(Value::new_integer(6, Width::I32)))]
+  fn test_digit_functions(input: &str) -> Result<Value, String> {
+    tokenize(input).and_then(parse).map_err(|err| err.to_string())
+  }
+
+  #[test_case("isqrt(0)" => Ok(Value::new_integer(0, Width::U64)))]
+  #[test_case("isqrt(15)" => Ok(Value::new_integer(3, Width::U64)))]
+  #[test_case("isqrt(16)" => Ok(Value::new_integer(4, Width::U64)))]
+  #[test_case("isqrt(u8(255))" => Ok(Value::new_integer(15, Width::U8)))]
+  #[test_case("isqrt(u64(0xFFFFFFFFFFFFFFFF))" => Ok(Value::new_integer(0xFFFFFFFF, Width::U64)); "exact for u64 max")]
+  #[test_case("isqrt(U128_MAX)" => Ok(Value::new_integer(u64::MAX as u128, Width::U128)); "exact for u128 max")]
+  #[test_case("ilog2(1)" => Ok(Value::new_integer(0, Width::U64)))]
+  #[test_case("ilog2(1023)" => Ok(Value::new_integer(9, Width::U64)))]
+  #[test_case("ilog2(1024)" => Ok(Value::new_integer(10, Width::U64)))]
+  #[test_case("ilog2(u8(255))" => Ok(Value::new_integer(7, Width::U8)))]
+  #[test_case("ilog2(U128_MAX)" => Ok(Value::new_integer(127, Width::U128)))]
+  #[test_case("ilog2(0)" => Err("ilog2 of a non-positive number".to_string()))]
+  #[test_case("ilog2(i8(-4))" => Err("ilog2 of a non-positive number".to_string()); "negative")]
+  #[test_case("ispow(u128(3) ** 80, 80)" => Ok(Value::new_integer(1, Width::U8)); "large power")]
+  #[test_case("ispow(u128(3) ** 80 - 1, 80)" => Ok(Value::new_integer(0, Width::U8)); "large non power")]
+  #[test_case("issquare(16)" => Ok(Value::new_integer(1, Width::U8)))]
+  #[test_case("issquare(15)" => Ok(Value::new_integer(0, Width::U8)))]
+  #[test_case("issquare(0)" => Ok(Value::new_integer(1, Width::U8)))]
+  #[test_case("issquare(i8(-4))" => Ok(Value::new_integer(0, Width::U8)))]
+  #[test_case("issquare(18446744065119617025)" => Ok(Value::new_integer(1, Width::U8)); "largest u64 square")]
+  #[test_case("issquare(16.5)" => Ok(Value::new_integer(0, Width::U8)))]
+  #[test_case("ispow(27, 3)" => Ok(Value::new_integer(1, Width::U8)))]
+  #[test_case("ispow(26, 3)" => Ok(Value::new_integer(0, Width::U8)))]
+  #[test_case("ispow(1000, 10)" => Ok(Value::new_integer(0, Width::U8)))]
+  #[test_case("ispow(1024, 5)" => Ok(Value::new_integer(1, Width::U8)))]
+  #[test_case("ispow(i8(-8), 3)" => Ok(Value::new_integer(1, Width::U8)))]
+  #[test_case("ispow(i8(-4), 2)" => Ok(Value::new_integer(0, Width::U8)))]
+  fn test_power_predicates(input: &str) -> Result<Value, String> {
+    tokenize(input).and_then(parse).map_err(|err| err.to_string())
+  }
+
+  #[test]
+  fn test_list_functions() {
+    let list = list_functions();
+    let lines = list.lines().collect::<Vec<_>>();
+    assert_eq!(lines.len(), 5);
+    assert!(lines[0].starts_with("operators: != % & && * ** + - / < << <="));
+    assert!(!lines[0].contains("-u"));
+    assert!(lines[1].starts_with("casts: f32 f64 i128 i16 i32 i64 i8 u128 u16"));
+    assert!(lines[2].contains(" c2f ") && lines[2].contains(" kilobyte "));
+    assert!(lines[3].contains(" sin ") && !lines[3].contains(" u8 "));
+    assert!(lines[4].starts_with("aliases: GiB (gigabyte) KiB (kilobyte)"));
+    assert!(lines[4].contains(" signum (sign) "));
+
+    let constants = list_constants();
+    assert!(constants.starts_with("E\nF64_MAX\nF64_MIN\n"));
+    assert!(constants.lines().any(|name| name == "PI"));
+  }
+
+  #[test_case("sinn" => Some("sin"))]
+  #[test_case("flor" => Some("floor"))]
+  #[test_case("pi" => Some("PI"))]
+  #[test_case("qwzxv" => None)]
+  fn test_closest_name(name: &str) -> Option<&'static str> {
+    closest_name(name)
+  }
+
+  #[test]
+  fn test_next_float() {
+    assert_eq!(next_up(1.0), 1.0 + f64::EPSILON);
+    assert_eq!(next_down(1.0), 1.0 - f64::EPSILON / 2.0);
+    assert_eq!(next_down(next_up(1.0)), 1.0);
+    assert_eq!(next_up(-1.0), -1.0 + f64::EPSILON / 2.0);
+    assert_eq!(next_up(0.0).to_bits(), 1);
+    assert_eq!(next_up(-0.0).to_bits(), 1);
+    assert_eq!(next_down(0.0), -f64::from_bits(1));
+    assert_eq!(next_up(f64::MAX), f64::INFINITY);
+    assert_eq!(next_up(f64::INFINITY), f64::INFINITY);
+    assert_eq!(next_up(f64::NEG_INFINITY), f64::MIN);
+    assert_eq!(next_down(f64::NEG_INFINITY), f64::NEG_INFINITY);
+    assert!(next_up(f64::NAN).is_nan());
+  }
+
+  #[test_case("nextup(1.0)" => Ok(Value::new_float(1.0 + f64::EPSILON)))]
+  #[test_case("nextdown(0)" => Ok(Value::new_float(-f64::from_bits(1))))]
+  fn test_next_float_functions(input: &str) -> Result<Value, String> {
+    tokenize(input).and_then(parse).map_err(|err| err.to_string())
+  }
+
+  #[test_case("clamp(5, 0, 3)" => Ok(Value::new_integer(3, Width::U64)))]
+  #[test_case("clamp(2, 0, 3)" => Ok(Value::new_integer(2, Width::U64)))]
+  #[test_case("clamp(i32(-2), 0, 10)" => Ok(Value::new_integer(0, Width::I32)))]
+  #[test_case("clamp(i8(-2), i8(-1), 10)" => Ok(Value::new_integer(-1i8 as u128, Width::I8)))]
+  #[test_case("clamp(1.5, 0, 1)" => Ok(Value::new_float(1.0)))]
+  #[test_case("clamp(5, 10, 0)" => Ok(Value::new_integer(10, Width::U64)); "lo greater than hi returns lo")]
+  fn test_clamp(input: &str) -> Result<Value, String> {
+    tokenize(input).and_then(parse).map_err(|err| err.to_string())
+  }
+
+  #[test_case("cbrt(27)" => Ok(Value::new_float(3.0)))]
+  #[test_case("cbrt(-8.0)" => Ok(Value::new_float(-2.0)); "negative")]
+  #[test_case("cbrt(i8(-8))" => Ok(Value::new_float(-2.0)); "negative integer")]
+  #[test_case("roundn(3.14159, 2)" => Ok(Value::new_float(314.0 / 100.0)))]
+  #[test_case("roundn(2.5, 0)" => Ok(Value::new_float(3.0)); "halfway away from zero")]
+  #[test_case("roundn(-2.5, 0)" => Ok(Value::new_float(-3.0)); "negative halfway away from zero")]
+  #[test_case("roundn(1234, -2)" => Ok(Value::new_float(1200.0)); "negative places")]
+  #[test_case("trunc(2.7)" => Ok(Value::new_float(2.0)))]
+  #[test_case("trunc(-2.7)" => Ok(Value::new_float(-2.0)); "trunc negative")]
+  #[test_case("fract(2.75)" => Ok(Value::new_float(0.75)))]
+  #[test_case("fract(-2.75)" => Ok(Value::new_float(-0.75)); "fract negative")]
+  #[test_case("sign(-3.5)" => Ok(Value::new_float(-1.0)))]
+  #[test_case("sign(0)" => Ok(Value::new_float(0.0)))]
+  #[test_case("signum(42)" => Ok(Value::new_float(1.0)))]
+  fn test_float_functions(input: &str) -> Result<Value, String> {
+    tokenize(input).and_then(parse).map_err(|err| err.to_string())
+  }
+
+  #[test_case("cosh(0)" => Ok(Value::new_float(1.0)))]
+  #[test_case("tanh(0)" => Ok(Value::new_float(0.0)))]
+  #[test_case("sinh(1)" => Ok(Value::new_float(1f64.sinh())))]
+  #[test_case("asinh(sinh(1))" => Ok(Value::new_float(1.0)))]
+  #[test_case("acosh(cosh(2))" => Ok(Value::new_float(2.0)))]
+  #[test_case("atanh(tanh(0.5))" => Ok(Value::new_float(0.5)))]
+  #[test_case("isnan(acosh(0.5))" => Ok(Value::new_integer(1, Width::U8)); "acosh below one")]
+  fn test_hyperbolic(input: &str) -> Result<Value, String> {
+    tokenize(input).and_then(parse).map_err(|err| err.to_string())
+  }
+
+  #[test_case("coalesce(0, 5)" => Ok(Value::new_integer(5, Width::U64)))]
+  #[test_case("coalesce(3, 5)" => Ok(Value::new_integer(3, Width::U64)))]
+  #[test_case("coalesce(NAN, 5)" => Ok(Value::new_integer(5, Width::U64)))]
+  #[test_case("coalesce(0.0, 2.5)" => Ok(Value::new_float(2.5)))]
+  #[test_case("coalesce(u8(0), i16(-1))" => Ok(Value::new_integer(-1i16 as u128, Width::I16)); "width of the default")]
+  #[test_case("coalesce(0K, 2K)" => Ok(Value::new(Number::from(2u64), Unit::Kilobyte)); "unit of the default")]
+  fn test_coalesce(input: &str) -> Result<Value, String> {
+    tokenize(input).and_then(parse).map_err(|err| err.to_string())
+  }
+
+  #[test_case("divor(10, 2, -1)" => Ok(Value::new_integer(5, Width::U64)))]
+  #[test_case("divor(10, 0, -1)" => Ok(Value::new_integer(-1i64 as u64 as u128, Width::U64)))]
+  #[test_case("divor(10, 0, -1i8)" => Ok(Value::new_integer(-1i8 as u128, Width::I8)); "type of the default")]
+  #[test_case("divor(7.5, 0.0, 0.5)" => Ok(Value::new_float(0.5)))]
+  #[test_case("divor(7.5, 2, 0)" => Ok(Value::new_float(3.75)))]
+  #[test_case("divor(2K, 2, 0)" => Ok(Value::new(Number::from(1u64), Unit::Kilobyte)))]
+  fn test_divor(input: &str) -> Result<Value, String> {
+    tokenize(input).and_then(parse).map_err(|err| err.to_string())
+  }
+
+  #[test_case("nCr(5, 2)" => Ok(Value::new_integer(10, Width::U64)))]
+  #[test_case("nPr(5, 2)" => Ok(Value::new_integer(20, Width::U64)))]
+  #[test_case("nCr(52, 5)" => Ok(Value::new_integer(2598960, Width::U64)))]
+  #[test_case("nCr(5, 0)" => Ok(Value::new_integer(1, Width::U64)))]
+  #[test_case("nCr(5, 6)" => Ok(Value::new_integer(0, Width::U64)))]
+  #[test_case("nCr(67, 33)" => Ok(Value::new_integer(14226520737620288370, Width::U64)); "fits in u64")]
+  #[test_case("nCr(68, 34)" => Err("overflow: nCr(68, 34) does not fit in u64".to_string()))]
+  #[test_case("nCr(10u8, 5)" => Ok(Value::new_integer(252, Width::U8)))]
+  #[test_case("nPr(10u8, 5)" => Err("overflow: nPr(10, 5) does not fit in u8".to_string()))]
+  #[test_case("nCr(-5i8, 2)" => Err("nCr of a negative number".to_string()))]
+  #[test_case("nPr(5.5, 2)" => Err("nPr of a non-integer".to_string()))]
+  fn test_combinatorics(input: &str) -> Result<Value, String> {
+    tokenize(input).and_then(parse).map_err(|err| err.to_string())
+  }
+
+  #[test_case("ord('A')" => Ok(Value::new_integer(65, Width::U64)))]
+  #[test_case("ord('A') == ord(chr(65))" => Ok(Value::new_integer(1, Width::U8)); "same as a number")]
+  #[test_case("u8(65) == 'A'" => Ok(Value::new_integer(1, Width::U8)); "compared in a typed width")]
+  #[test_case("chr(65)" => Ok(Value::new(Number::from(65u64), Unit::Char)))]
+  #[test_case("chr('a' - 32)" => Ok(Value::new(Number::from(65u64), Unit::Char)); "arithmetic on a literal")]
+  #[test_case("chr(200)" => Err("200 is not an ASCII code point".to_string()))]
+  #[test_case("chr(-1i8)" => Err("-1 is not an ASCII code point".to_string()))]
+  #[test_case("chr(65.0)" => Err("65 is not an ASCII code point".to_string()))]
+  #[test_case("ord(chr(66))" => Ok(Value::new_integer(66, Width::U64)))]
+  fn test_char_functions(input: &str) -> Result<Value, String> {
+    tokenize(input).and_then(parse).map_err(|err| err.to_string())
+  }
+
+  #[test_case("chr(65)" => "A")]
+  #[test_case("chr(65) + 1" => "B")]
+  #[test_case("97 to chr" => "a")]
+  #[test_case("chr(127) + 1" => "128"; "outside ascii")]
+  fn test_char_display(input: &str) -> String {
+    tokenize(input).and_then(parse).unwrap().to_string()
+  }
+
+  #[test_case("ipv4(0xC0A80001)" => Ok("192.168.0.1".to_string()))]
+  #[test_case("ipv4(10.0.0.255) + 1" => Ok("10.0.1.0".to_string()))]
+  #[test_case("ipv4(U32_MAX)" => Ok("255.255.255.255".to_string()))]
+  #[test_case("3232235521 to ipv4" => Ok("192.168.0.1".to_string()))]
+  #[test_case("192.168.0.1 - 192.168.0.0" => Ok("1".to_string()); "literals are raw")]
+  #[test_case("ipv4(1 << 32)" => Err("4294967296 is not an IPv4 address".to_string()))]
+  fn test_ipv4(input: &str) -> Result<String, String> {
+    tokenize(input)
+      .and_then(parse)
+      .map(|v| v.to_string())
+      .map_err(|err| err.to_string())
+  }
+
+  #[test_case("tryu8(255)" => Ok(Value::new_integer(255, Width::U8)))]
+  #[test_case("tryu8(256)" => Err("value 256 does not fit in u8".to_string()))]
+  #[test_case("tryu8(-1i8)" => Err("value -1 does not fit in u8".to_string()))]
+  #[test_case("tryi8(-128i16)" => Ok(Value::new_integer(-128i8 as u128, Width::I8)))]
+  #[test_case("tryi8(128)" => Err("value 128 does not fit in i8".to_string()))]
+  #[test_case("tryu64(-1)" => Ok(Value::new_integer(u64::MAX as u128, Width::U64)); "bare negation wraps first")]
+  #[test_case("tryu32(4.0)" => Ok(Value::new_integer(4, Width::U32)))]
+  #[test_case("tryu32(4.5)" => Err("value 4.5 does not fit in u32".to_string()))]
+  #[test_case("tryu128(2.0 ** 100)" => Ok(Value::new_integer(1 << 100, Width::U128)); "whole float")]
+  #[test_case("tryu64(2.0 ** 100)" => Err("value 1267650600228229400000000000000 does not fit in u64".to_string()); "float too large")]
+  #[test_case("tryu16(1K)" => Ok(Value::new(Number::from(1u64), Unit::Kilobyte)); "size in bytes")]
+  fn test_try_cast(input: &str) -> Result<Value, String> {
+    tokenize(input).and_then(parse).map_err(|err| err.to_string())
+  }
+
+  #[test_case("unsigned(i8(-1))" => Ok(Value::new_integer(255, Width::U8)))]
+  #[test_case("signed(u8(255))" => Ok(Value::new_integer(-1i8 as u128, Width::I8)))]
+  #[test_case("signed(0x8000u16)" => Ok(Value::new_integer(i16::MIN as u128, Width::I16)))]
+  #[test_case("unsigned(-2i32)" => Ok(Value::new_integer(0xFFFF_FFFE, Width::U32)))]
+  #[test_case("unsigned(-1i64)" => Ok(Value::new_integer(u64::MAX as u128, Width::U64)))]
+  #[test_case("signed(U64_MAX)" => Ok(Value::new_integer(-1i64 as u128, Width::I64)))]
+  #[test_case("unsigned(-1i128)" => Ok(Value::new_integer(u128::MAX, Width::U128)))]
+  #[test_case("signed(-5i8)" => Ok(Value::new_integer(-5i8 as u128, Width::I8)); "already signed")]
+  #[test_case("unsigned(7u32)" => Ok(Value::new_integer(7, Width::U32)); "already unsigned")]
+  #[test_case("unsigned(signed(200u8))" => Ok(Value::new_integer(200, Width::U8)); "round trip")]
+  #[test_case("signed(1.5)" => Err("signed of a float".to_string()))]
+  #[test_case("unsigned(-1.0)" => Err("unsigned of a float".to_string()))]
+  fn test_reinterpret_sign(input: &str) -> Result<Value, String> {
+    tokenize(input).and_then(parse).map_err(|err| err.to_string())
+  }
+
+  #[test_case("randint(5, 5)" => Ok(Value::new_integer(5, Width::U64)))]
+  #[test_case("randint(-1i8, -1i8)" => Ok(Value::new_integer(-1i8 as u128, Width::I8)))]
+  #[test_case("randint(3, 1)" => Err("randint of an empty range".to_string()))]
+  #[test_case("rand(1)" => Err("Expected no arguments to rand".to_string()))]
+  fn test_random(input: &str) -> Result<Value, String> {
+    tokenize(input).and_then(parse).map_err(|err| err.to_string())
+  }
+
+  #[test]
+  fn test_random_range() {
+    let eval = |input| tokenize(input).and_then(parse).unwrap().number;
+    for _ in 0..100 {
+      assert!((1..=6).contains(&u64::from(eval("randint(1, 6)"))));
+      assert!((0.0..1.0).contains(&f64::from(eval("rand()"))));
+    }
+  }
+
+  #[test]
+  fn test_random_seed() {
+    use crate::settings::{set_settings, Settings};
+
+    // each thread starts its generator from the seed
+    let randint = || {
+      set_settings(Settings {
+        seed: Some(42),
+        ..Settings::default()
+      });
+      tokenize("randint(1, 1000000)").and_then(parse).unwrap()
+    };
+    let first = std::thread::spawn(randint).join().unwrap();
+    let second = std::thread::spawn(randint).join().unwrap();
+    assert_eq!(first, second);
+  }
+
+  #[test_case("rem_euclid(-7i64, 3)" => Ok(Value::new_integer(2, Width::I64)))]
+  #[test_case("rem_euclid(7, 3)" => Ok(Value::new_integer(1, Width::U64)))]
+  #[test_case("rem_euclid(-7i64, -3)" => Ok(Value::new_integer(2, Width::I64)); "negative divisor")]
+  #[test_case("rem_euclid(i8(-128), 3)" => Ok(Value::new_integer(1, Width::I8)))]
+  #[test_case("rem_euclid(-7.5, 2)" => Ok(Value::new_float(0.5)))]
+  #[test_case("rem_euclid(7, 0)" => Err("division by zero".to_string()))]
+  #[test_case("-7i64 % 3" => Ok(Value::new_integer(-1i64 as u128, Width::I64)))]
+  #[test_case("-7i64 / 2" => Ok(Value::new_integer(-3i64 as u128, Width::I64)))]
+  #[test_case("i8(10) / -2.0" => Ok(Value::new_integer(-5i8 as u128, Width::I8)))]
+  #[test_case("rem(-7i64, 3)" => Ok(Value::new_integer(-1i64 as u128, Width::I64)))]
+  #[test_case("mod(-7i64, 3)" => Ok(Value::new_integer(2, Width::I64)))]
+  #[test_case("rem(7i64, -3)" => Ok(Value::new_integer(1, Width::I64)))]
+  #[test_case("mod(7i64, -3)" => Ok(Value::new_integer(1, Width::I64)))]
+  #[test_case("rem(i8(-8), 4)" => Ok(Value::new_integer(0, Width::I8)))]
+  #[test_case("rem(-7.5, 2)" => Ok(Value::new_float(-1.5)))]
+  #[test_case("mod(-7.5, 2)" => Ok(Value::new_float(0.5)))]
+  #[test_case("rem(17, 5)" => Ok(Value::new_integer(2, Width::U64)))]
+  #[test_case("rem(7, 0)" => Err("division by zero".to_string()))]
+  #[test_case("mod(7, 0)" => Err("division by zero".to_string()))]
+  fn test_rem_euclid(input: &str) -> Result<Value, String> {
+    tokenize(input).and_then(parse).map_err(|err| err.to_string())
+  }
+
+  #[test_case("geomean(1, 4, 16)" => Ok(Value::new_float(4.0)))]
+  #[test_case("geomean(2, 8)" => Ok(Value::new_float(4.0)))]
+  #[test_case("geomean(0, 5)" => Ok(Value::new_float(0.0)))]
+  #[test_case("geomean(3)" => Ok(Value::new_float(3.0)))]
+  #[test_case("geomean(1, -4.0)" => Err("geomean of a negative number".to_string()))]
+  #[test_case("harmmean(1, 2, 4)" => Ok(Value::new_float(12.0 / 7.0)))]
+  #[test_case("harmmean(1, 0)" => Err("harmmean of a non-positive number".to_string()))]
+  #[test_case("harmmean(1, 2) + 1" => Ok(Value::new_float(7.0 / 3.0)))]
+  #[test_case("geomean()" => Err("Expected one argument to geomean".to_string()))]
+  fn test_means(input: &str) -> Result<Value, String> {
+    tokenize(input).and_then(parse).map_err(|err| err.to_string())
+  }
+
+  #[test]
+  fn test_f32_rounding() {
+    let single = f64::from(tokenize("f32(0.1)").and_then(parse).unwrap().number);
+    assert_eq!(single, 0.1f32 as f64);
+    assert_ne!(single, 0.1);
+    assert_eq!(
+      tokenize("f32(0.1) == 0.1").and_then(parse),
+      Ok(Value::new_integer(0, Width::U8))
+    );
+    assert_eq!(tokenize("f32(0.5)").and_then(parse), Ok(Value::new_float(0.5)));
+  }
+
+  #[test]
+  fn test_gray_round_trip() {
+    for x in [0u64, 1, 5, 42, 255, 1 << 40, u64::MAX] {
+      let input = format!("ungray(gray({}))", x);
+      assert_eq!(
+        tokenize(&input).and_then(parse),
+        Ok(Value::new_integer(x.into(), Width::U64))
+      );
+    }
+    assert_eq!(
+      tokenize("ungray(gray(i16(-300)))").and_then(parse),
+      Ok(Value::new_integer(-300i16 as u16 as u128, Width::I16))
+    );
+  }
+
+  #[test_case("u16(1 K)" => Ok("1K".to_string()))]
+  #[test_case("u8(1K)" => Ok("0K".to_string()); "size wraps in bytes")]
+  #[test_case("i8(100°C)" => Ok("100°C".to_string()))]
+  #[test_case("f64(2s)" => Ok("2s".to_string()))]
+  #[test_case("u8(300)" => Ok("44".to_string()))]
+  fn test_cast_keeps_unit(input: &str) -> Result<String, String> {
+    tokenize(input)
+      .and_then(parse)
+      .map(|value| value.to_string())
+      .map_err(|err| err.to_string())
+  }
+
+  #[test_case("turns(1)" => Ok(Value::new_float(std::f64::consts::TAU)))]
+  #[test_case("turns(0.25)" => Ok(Value::new_float(std::f64::consts::FRAC_PI_2)))]
+  #[test_case("todeg(PI)" => Ok(Value::new_float(180.0)))]
+  #[test_case("torad(180)" => Ok(Value::new_float(std::f64::consts::PI)))]
+  #[test_case("todeg(turns(1))" => Ok(Value::new_float(360.0)))]
+  fn test_angle_functions(input: &str) -> Result<Value, String> {
+    tokenize(input).and_then(parse).map_err(|err| err.to_string())
+  }
+
+  #[test_case("c2f(100°C)" => Ok(Value::new(Number::from(212.0), Unit::Fahrenheit)))]
+  #[test_case("c2f(100)" => Ok(Value::new(Number::from(100.0), Unit::Fahrenheit)))]
+  #[test_case("f2c(212°F)" => Ok(Value::new(Number::from(100.0), Unit::Celsius)))]
+  #[test_case("c2f(212°F)" => Err("Conversion from °F to °F is not supported".to_string()))]
+  #[test_case("f2c(1K)" => Err("Conversion from K to °C is not supported".to_string()))]
+  fn test_directed_conversions(input: &str) -> Result<Value, String> {
+    tokenize(input).and_then(parse).map_err(|err| err.to_string())
+  }
+
+  #[test_case("5 - 10", false => Ok(Value::new_integer(5u64.wrapping_sub(10).into(), Width::U64)); "wrap")]
+  #[test_case("5 - 10", true => Ok(Value::new_integer(-5i64 as u128, Width::I64)); "signed sub")]
+  #[test_case("u8(5) - 10", true => Ok(Value::new_integer(-5i8 as u128, Width::I8)); "signed sub u8")]
+  #[test_case("10 - 5", true => Ok(Value::new_integer(5, Width::U64)); "signed sub no underflow")]
+  fn test_signed_sub(input: &str, signed_sub: bool) -> Result<Value, String> {
+    set_settings(Settings {
+      signed_sub,
+      ..Settings::default()
+    });
+    tokenize(input).and_then(parse).map_err(|err| err.to_string())
+  }
+
+  #[test_case("u8(200) + u8(100)", false => Ok(Value::new_integer(44, Width::U8)); "add wraps")]
+  #[test_case("u8(200) + u8(100)", true => Err("overflow: 300 does not fit in u8".to_string()); "add overflows")]
+  #[test_case("u8(200) + u8(55)", true => Ok(Value::new_integer(255, Width::U8)); "add fits")]
+  #[test_case("u8(16) * u8(16)", false => Ok(Value::new_integer(0, Width::U8)); "mul wraps")]
+  #[test_case("u8(16) * u8(16)", true => Err("overflow: 256 does not fit in u8".to_string()); "mul overflows")]
+  #[test_case("i8(-64) * 2i64", true => Ok(Value::new_integer(-128i64 as u128, Width::I64)); "mul promoted")]
+  #[test_case("i8(-64) * 3", true => Err("overflow: -192 does not fit in i8".to_string()); "mul literal overflows")]
+  #[test_case("i8(-64) * 3i8", true => Err("overflow: -192 does not fit in i8".to_string()); "mul overflows signed")]
+  #[test_case("u8(2)u8(200)", true => Err("overflow: 400 does not fit in u8".to_string()); "implicit mul overflows")]
+  #[test_case("0 - 1", true => Err("overflow: -1 does not fit in u64".to_string()); "sub overflows")]
+  #[test_case("U64_MAX + 1.5", true => Err("overflow: 18446744073709551616 does not fit in u64".to_string()); "float operand overflows")]
+  #[test_case("u8(5) + -1.5", true => Err("overflow: -1 does not fit in u8".to_string()); "float operand doesn't fit")]
+  #[test_case("u8(5) + 1.5", true => Ok(Value::new_integer(6, Width::U8)); "float operand fits")]
+  fn test_checked(input: &str, checked: bool) -> Result<Value, String> {
+    set_settings(Settings {
+      checked,
+      ..Settings::default()
+    });
+    let result = tokenize(input).and_then(parse).map_err(|err| err.to_string());
+    set_settings(Settings::default());
+    result
+  }
+
+  #[test_case("u8(200) + u8(100)", true => Value::new_integer(255, Width::U8); "unsigned upper bound")]
+  #[test_case("u8(10) - u8(20)", true => Value::new_integer(0, Width::U8); "unsigned lower bound")]
+  #[test_case("u16(300) * u16(300)", true => Value::new_integer(0xFFFF, Width::U16); "unsigned mul")]
+  #[test_case("i8(100) + i8(50)", true => Value::new_integer(127, Width::I8); "signed upper bound")]
+  #[test_case("i8(-100) - i8(50)", true => Value::new_integer(-128i8 as u128, Width::I8); "signed lower bound")]
+  #[test_case("i8(-128) / i8(-1)", true => Value::new_integer(127, Width::I8); "signed div")]
+  #[test_case("i8(-100) - i8(50)", false => Value::new_integer(106, Width::I8); "wraps by default")]
+  #[test_case("u8(200) + 300", true => Value::new_integer(500, Width::U64); "promoted width")]
+  #[test_case("u8(200) + 100", true => Value::new_integer(255, Width::U8); "literal width")]
+  fn test_saturating(input: &str, saturating: bool) -> Value {
+    set_settings(Settings {
+      saturating,
+      ..Settings::default()
+    });
+    let result = tokenize(input).and_then(parse).unwrap();
+    set_settings(Settings::default());
+    result
+  }
+
+  #[test_case("-1i8 < 255u8" => (Value::new_integer(0, Width::U8), vec!["comparing i8 with u8; using i8 semantics".to_string()]); "255u8 is -1 as i8")]
+  #[test_case("255u8 > -1i8" => (Value::new_integer(0, Width::U8), vec!["comparing u8 with i8; using u8 semantics".to_string()]); "-1i8 is 255 as u8")]
+  #[test_case("-1i8 < 1i16" => (Value::new_integer(1, Width::U8), vec![]); "same signedness")]
+  fn test_mixed_signedness_warning(input: &str) -> (Value, Vec<String>) {
+    set_settings(Settings {
+      warn: true,
+      ..Settings::default()
+    });
+    let value = tokenize(input).and_then(parse).unwrap();
+    (value, take_warnings())
+  }
+
+  #[test_case("fact(5)" => Ok(Value::new_integer(120, Width::U64)))]
+  #[test_case("fact(0)" => Ok(Value::new_integer(1, Width::U64)))]
+  #[test_case("fact(5u8)" => Ok(Value::new_integer(120, Width::U8)))]
+  #[test_case("fact(6u8)" => Ok(Value::new_integer(720 % 256, Width::U8)))]
+  #[test_case("fact(-1i8)" => Err("factorial of a negative number".to_string()))]
+  fn test_factorial(input: &str) -> Result<Value, String> {
+    tokenize(input).and_then(parse).map_err(|err| err.to_string())
+  }
+
+  #[test_case("2 ** (2 ** 32 + 1)" => Err("exponent 4294967297 is out of range for a big integer".to_string()))]
+  #[test_case("1 << -1" => Err("shift count -1 is out of range for a big integer".to_string()))]
+  #[test_case("1 << 100" => Ok("1267650600228229401496703205376".to_string()))]
+  fn test_bigint_count(input: &str) -> Result<String, String> {
+    set_settings(Settings {
+      bigint: true,
+      ..Settings::default()
+    });
+    tokenize(input)
+      .and_then(parse)
+      .map(|value| value.to_string())
+      .map_err(|err| err.to_string())
+  }
+
+  #[test_case("2 ** 100" => "1267650600228229401496703205376")]
+  #[test_case("fact(30)" => "265252859812191058636308480000000")]
+  #[test_case("fact(30) / fact(28)" => "870")]
+  #[test_case("-(2 ** 64) + 1" => "-18446744073709551615")]
+  #[test_case("0xFFFFFFFFFFFFFFFFFF" => "4722366482869645213695")]
+  #[test_case("2u8 ** 100" => "0"; "typed literal wraps")]
+  #[test_case("2 ** 100 as u64" => "0"; "cast wraps")]
+  fn test_bigint(input: &str) -> String {
+    set_settings(Settings {
+      bigint: true,
+      ..Settings::default()
+    });
+    parse(tokenize(input).unwrap()).unwrap().to_string()
+  }
 }
@@ -1,21 +1,36 @@
+use crate::error::ClcError;
+use crate::functions::{cast_to, is_cast};
+use crate::settings::settings;
 use crate::value::{Number, Unit, Value};
 use logos::{Lexer, Logos};
+use num_bigint::BigInt;
+use std::net::Ipv4Addr;
+use std::ops::Range;
 use std::str::FromStr;
 
+/// The byte range of the input that a token was read from.
+pub type Span = Range<usize>;
+
 /// A final token produced by the lexer.
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub enum Token {
   Value(Value),
+  /// An integer literal without a type, which takes the width of a typed operand it fits in.
+  Literal(Value),
   Identifier(String),
   Operator(String),
   LParen,
   RParen,
+  Comma,
   Newline,
+  /// A call of a variadic function with its number of arguments (only produced by the parser).
+  Call(String, usize),
 }
 
+#[allow(dead_code)]
 impl Token {
   pub fn is_value(&self) -> bool {
-    matches!(self, Token::Value(_))
+    matches!(self, Token::Value(_) | Token::Literal(_))
   }
 
   pub fn is_identifier(&self) -> bool {
@@ -27,13 +42,17 @@ impl Token {
   }
 
   pub fn is_binary_op(&self) -> bool {
-    matches!(self, Token::Operator(op) if !op.ends_with("u"))
+    matches!(self, Token::Operator(op) if !op.ends_with("u") && !op.ends_with("p"))
   }
 
   pub fn is_unary_op(&self) -> bool {
     matches!(self, Token::Operator(op) if op.ends_with("u"))
   }
 
+  pub fn is_postfix_op(&self) -> bool {
+    matches!(self, Token::Operator(op) if op.ends_with("p"))
+  }
+
   pub fn is_lparen(&self) -> bool {
     matches!(self, Token::LParen)
   }
@@ -42,11 +61,22 @@ impl Token {
     matches!(self, Token::RParen)
   }
 
+  pub fn is_comma(&self) -> bool {
+    matches!(self, Token::Comma)
+  }
+
   pub fn is_newline(&self) -> bool {
     matches!(self, Token::Newline)
   }
 }
 
+/// A token and the span of the input it was read from.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Spanned {
+  pub token: Token,
+  pub span: Span,
+}
+
 impl From<Number> for Token {
   fn from(number: Number) -> Self {
     Token::Value(Value::new_number(number))
@@ -62,20 +92,30 @@ impl From<Value> for Token {
 /// An intermediate token produced by the lexer.
 #[derive(Logos, Clone, Debug, PartialEq)]
 pub enum RawToken {
-  // eg. 101, 0x1F, 0o777, 0b1101
-  #[regex(r"0x[0-9a-fA-F]+|0o[0-7]+|0b[01]+|[0-9]+", conv_integer)]
-  Integer(u64),
-  // eg. 3.141, 0.0001, 2., .5
+  // eg. 101, 0x1F, 0o777, 0b1101 (None if the literal is wider than 128 bits)
+  #[regex(r"0x[0-9a-fA-F]+|0o[0-7]+|0b[01]+|[0-9]+", |lex| Some(conv_integer(lex)))]
+  Integer(Option<u128>),
+  // eg. $FF (only with --asm-syntax)
+  #[regex(r"\$[0-9a-fA-F]+")]
+  DollarHex,
+  // eg. 3.141, 0.0001, 2., .5, 0x1.8p3
   #[regex(r"\d+\.\d*|\.\d+", conv_float)]
+  #[regex(r"0x([0-9a-fA-F]+(\.[0-9a-fA-F]*)?|\.[0-9a-fA-F]+)[pP][+-]?[0-9]+", conv_hexfloat)]
   Float(f64),
-  // eg. G, kg, °C
-  #[regex(r"([BKMGTP]|°[CFK]?)", priority = 2)]
+  // eg. 192.168.0.1 (None if an octet is out of range)
+  #[regex(r"\d+\.\d+\.\d+\.\d+", |lex| lex.slice().parse::<Ipv4Addr>().ok())]
+  Ipv4(Option<Ipv4Addr>),
+  // eg. 'A', '\n' (printable ASCII or an escape)
+  #[regex(r"'([ -&(-\[\]-~]|\\[nrt0\\'])'", conv_char)]
+  Char(u32),
+  // eg. G, GB, °C (but not s, which is only a unit directly after a number)
+  #[regex(r"([BKMGTP]|[KMGTP]B|°[CFK]?)", priority = 2)]
   Unit,
-  // eg. sin, cos, PI
-  #[regex(r"[a-zA-Z][a-zA-Z0-9_]*")]
+  // eg. sin, cos, PI, _
+  #[regex(r"[a-zA-Z_][a-zA-Z0-9_]*")]
   Identifier,
   // eg. *, /, %, &
-  #[regex(r"==|!=|>|<|>=|<=|&|\||\^|<<|>>|&&|\|\||~|!|\+|-|\*|/|%")]
+  #[regex(r"==|!=|>|<|>=|<=|&|\||\^\^|\^|<<|>>|&&|\|\||~|!|\+|-|\*\*|\*|/|%|\?|:|=")]
   Operator,
   // eg. (
   #[token("(")]
@@ -83,26 +123,106 @@ pub enum RawToken {
   // eg. )
   #[token(")")]
   RParen,
+  // eg. ,
+  #[token(",")]
+  Comma,
   // eg. \n
   #[token("\n")]
   Newline,
 
   #[regex(r"[ \t]+", logos::skip)] // skip whitespace
+  #[regex(r"(#|//)[^\n]*", logos::skip)] // skip comments
   #[error]
   Error,
 }
 
-fn conv_integer(lex: &mut Lexer<RawToken>) -> Option<u64> {
-  let slice = lex.slice();
-
-  let (slice, radix) = match slice {
+/// Splits an integer literal into its digits and radix.
+fn split_radix(slice: &str) -> (&str, u32) {
+  match slice {
     s if s.starts_with("0x") => (&s[2..], 16),
     s if s.starts_with("0o") => (&s[2..], 8),
     s if s.starts_with("0b") => (&s[2..], 2),
     s => (s, 10),
+  }
+}
+
+fn conv_integer(lex: &mut Lexer<RawToken>) -> Option<u128> {
+  let (slice, radix) = split_radix(lex.slice());
+  u128::from_str_radix(slice, radix).ok()
+}
+
+/// Parses an assembler-style integer literal (e.g. `$FF`, `0FFh` or `1101b`).
+fn asm_integer(slice: &str) -> Option<u128> {
+  let (digits, radix) = if let Some(digits) = slice.strip_prefix('$') {
+    (digits, 16)
+  } else if let Some(digits) = slice.strip_suffix(['h', 'H']) {
+    // a hex suffix needs a leading digit to tell it apart from an identifier (e.g. `0FFh`)
+    (digits.starts_with(|c: char| c.is_ascii_digit()).then_some(digits)?, 16)
+  } else {
+    (slice.strip_suffix('b')?, 2)
   };
+  digits
+    .chars()
+    .all(|c| c.is_digit(radix))
+    .then(|| u128::from_str_radix(digits, radix).ok())?
+}
+
+fn conv_big(slice: &str) -> BigInt {
+  let (slice, radix) = split_radix(slice);
+  // the integer regex only matches valid digits for the radix
+  BigInt::parse_bytes(slice.as_bytes(), radix).unwrap()
+}
+
+/// Returns a bare integer literal in the default width, which it must fit in without wrapping.
+/// Without a default width, a literal is a `u64` unless it only fits in a `u128`.
+fn default_width_integer(v: u128, slice: &str) -> Result<Number, ClcError> {
+  match settings().width {
+    Some(width) if v > width.max() => Err(ClcError::WidthOverflow {
+      slice: slice.to_string(),
+      width,
+    }),
+    Some(width) => Ok(Number::new_integer(v, width)),
+    None => Ok(u64::try_from(v).map_or(Number::from(v), Number::from)),
+  }
+}
+
+/// Whether the current token is directly followed by a type, making it a typed literal.
+fn is_typed_literal(lexer: &Lexer<RawToken>) -> bool {
+  let end = lexer.span().end;
+  let mut next = lexer.clone();
+  matches!(next.next(), Some(RawToken::Identifier) if next.span().start == end && is_cast(next.slice()))
+}
 
-  return u64::from_str_radix(slice, radix).ok();
+/// Returns the multiplier of a lowercase SI suffix directly after a number (e.g. `10k`).
+fn si_multiplier(suffix: &str) -> Option<u64> {
+  match suffix {
+    "k" => Some(1000),
+    "m" => Some(1000u64.pow(2)),
+    "g" => Some(1000u64.pow(3)),
+    "t" => Some(1000u64.pow(4)),
+    _ => None,
+  }
+}
+
+/// Whether a number starts with a radix prefix (e.g. `0x10`), so a letter after it isn't an SI
+/// suffix.
+fn has_radix_prefix(slice: &str) -> bool {
+  ["0x", "0o", "0b", "$"].iter().any(|prefix| slice.starts_with(prefix))
+}
+
+/// Scales a raw number by the multiplier of an SI suffix, keeping it a raw number.
+fn apply_multiplier(number: Number, multiplier: u64, slice: &str) -> Result<Number, ClcError> {
+  match number {
+    Number::Integer(v, w) => match v.checked_mul(multiplier as u128) {
+      Some(v) => default_width_integer(v, slice),
+      None => Err(ClcError::IntegerOverflow {
+        slice: slice.to_string(),
+        bits: w.bits(),
+      }),
+    },
+    Number::Big(v) => Ok(Number::from(v * multiplier)),
+    Number::Float(v) => Ok(Number::from(v * multiplier as f64)),
+  }
 }
 
 fn conv_float(lex: &mut Lexer<RawToken>) -> Option<f64> {
@@ -110,66 +230,252 @@ fn conv_float(lex: &mut Lexer<RawToken>) -> Option<f64> {
   f64::from_str(slice).ok()
 }
 
+/// Returns the code point of a character literal, which may be an escape (e.g. `'\n'`).
+fn conv_char(lex: &mut Lexer<RawToken>) -> u32 {
+  let slice = lex.slice();
+  match &slice[1..slice.len() - 1] {
+    "\\n" => '\n' as u32,
+    "\\r" => '\r' as u32,
+    "\\t" => '\t' as u32,
+    "\\0" => 0,
+    escaped if escaped.starts_with('\\') => escaped.chars().nth(1).unwrap() as u32,
+    c => c.chars().next().unwrap() as u32,
+  }
+}
+
+/// Parses a hexadecimal float literal (e.g. `0x1.8p3`), whose hex mantissa is scaled by a power
+/// of two. The mantissa may have at most 32 hex digits.
+fn conv_hexfloat(lex: &mut Lexer<RawToken>) -> Option<f64> {
+  let (mantissa, exp) = lex.slice()[2..].split_once(['p', 'P'])?;
+  let (int, frac) = mantissa.split_once('.').unwrap_or((mantissa, ""));
+  let digits = u128::from_str_radix(&format!("{}{}", int, frac), 16).ok()?;
+  // each fraction digit shifts the mantissa by four bits
+  let exp = i32::from_str(exp).ok()?.checked_sub(4 * frac.len() as i32)?;
+  if digits == 0 {
+    // zero scaled by an infinite power would be NaN
+    return Some(0.0);
+  }
+  // the digits are rounded once, and scaling by a power of two is exact unless it over- or
+  // underflows (in two steps, as the smallest subnormal is below the smallest power `powi` gives)
+  Some(digits as f64 * 2f64.powi(exp / 2) * 2f64.powi(exp - exp / 2))
+}
+
 //
 
-pub fn tokenize(input: &str) -> Result<Vec<Token>, String> {
+pub fn tokenize(input: &str) -> Result<Vec<Spanned>, ClcError> {
   let mut lexer = RawToken::lexer(input);
   let mut tokens: Vec<Token> = Vec::new();
+  let mut spans: Vec<Span> = Vec::new();
+  let mut prev_end = 0;
 
   while let Some(token) = lexer.next() {
+    // whether the token directly follows the previous one without any whitespace
+    let adjacent = lexer.span().start == prev_end;
+    prev_end = lexer.span().end;
+
+    // a '%' followed by a binary operator, ')' or the end of the line is a percentage rather than
+    // modulo, but a unary operator starts the right-hand side. A sign is unary when it is written
+    // against its operand but apart from the '%' (e.g. `10 % -3`, but not `10% - 3` or `10%-3`).
+    let unary_sign =
+      matches!(lexer.slice(), "+" | "-") && !adjacent && lexer.remainder().starts_with(|c: char| !c.is_whitespace());
+    if matches!(
+      token,
+      RawToken::Operator | RawToken::RParen | RawToken::Comma | RawToken::Newline
+    ) && !matches!(lexer.slice(), "!" | "~")
+      && !unary_sign
+    {
+      mark_percent(&mut tokens);
+    }
+
     match token {
-      RawToken::Integer(i) => tokens.push(Token::from(Number::from(i))),
+      RawToken::Integer(_) if settings().bigint => tokens.push(Token::from(Number::from(conv_big(lexer.slice())))),
+      RawToken::Integer(Some(i)) => {
+        // a typed literal (e.g. `300u16`) has its own width instead of the default
+        let token = if is_typed_literal(&lexer) {
+          Token::from(Number::from(i))
+        } else {
+          Token::Literal(Value::from(default_width_integer(i, lexer.slice())?))
+        };
+        tokens.push(token);
+      }
+      RawToken::Integer(None) => {
+        return Err(ClcError::IntegerOverflow {
+          slice: lexer.slice().to_string(),
+          bits: 128,
+        });
+      }
+      RawToken::DollarHex if settings().asm_syntax => {
+        let i = asm_integer(lexer.slice()).ok_or_else(|| ClcError::IntegerOverflow {
+          slice: lexer.slice().to_string(),
+          bits: 128,
+        })?;
+        let token = if is_typed_literal(&lexer) {
+          Token::from(Number::from(i))
+        } else {
+          Token::Literal(Value::from(default_width_integer(i, lexer.slice())?))
+        };
+        tokens.push(token);
+      }
+      RawToken::DollarHex => {
+        return Err(ClcError::Lex {
+          slice: lexer.slice().to_string(),
+          position: lexer.span().start,
+        });
+      }
       RawToken::Float(f) => tokens.push(Token::from(Number::from(f))),
+      // an address is packed with its first octet as the most significant byte (network byte order)
+      RawToken::Ipv4(Some(addr)) => tokens.push(Token::from(Number::from(u32::from(addr)))),
+      RawToken::Ipv4(None) => {
+        return Err(ClcError::Syntax(format!("Invalid IPv4 address '{}'", lexer.slice())));
+      }
+      // a character is a bare integer, like the code point written as a number
+      RawToken::Char(c) => tokens.push(Token::Literal(Value::from(default_width_integer(
+        u128::from(c),
+        lexer.slice(),
+      )?))),
+      RawToken::Unit if matches!(tokens.last(), Some(Token::Identifier(id)) if id == "to" || id == "in") => {
+        // the target of a conversion (e.g. `to °F`) is the unit's conversion function
+        let unit = Unit::from_str(lexer.slice()).unwrap();
+        tokens.push(Token::Identifier(unit.name().to_string()));
+      }
       RawToken::Unit => {
-        let number = tokens.pop().ok_or("Expexted number before unit")?;
+        let number = tokens
+          .pop()
+          .ok_or_else(|| ClcError::Syntax("Expected number before unit".to_string()))?;
         let unit = Unit::from_str(lexer.slice()).unwrap();
         let value = match number {
-          Token::Value(v) => Value::new(v.number, unit),
-          _ => return Err(format!("Unexpected token before unit '{}'", lexer.slice())),
+          Token::Value(v) | Token::Literal(v) => Value::new(v.number, unit),
+          _ => {
+            return Err(ClcError::Syntax(format!(
+              "Unexpected token before unit '{}'",
+              lexer.slice()
+            )))
+          }
         };
 
         tokens.push(Token::Value(value));
       }
+      RawToken::Identifier
+        if lexer.slice() == "s" && matches!(tokens.last(), Some(Token::Identifier(id)) if id == "to" || id == "in") =>
+      {
+        // the target of a conversion (e.g. `to s`) is the unit's conversion function
+        tokens.push(Token::Identifier(Unit::Second.name().to_string()));
+      }
+      RawToken::Identifier
+        if lexer.slice() == "s"
+          && adjacent
+          && matches!(tokens.last(), Some(Token::Value(v) | Token::Literal(v)) if v.is_raw()) =>
+      {
+        // an `s` is only the seconds unit directly after a number (e.g. `10s`), so that it can
+        // otherwise still be used as a name (e.g. `f(s) = s * 2`)
+        if let Some(Token::Value(v) | Token::Literal(v)) = tokens.pop() {
+          tokens.push(Token::Value(Value::new(v.number, Unit::Second)));
+        }
+      }
+      RawToken::Identifier
+        if adjacent
+          && settings().asm_syntax
+          && !spans.is_empty()
+          && asm_integer(&lexer.source()[spans.last().unwrap().start..lexer.span().end]).is_some() =>
+      {
+        // an assembler-style suffix (e.g. `0FFh` or `1101b`) re-reads the digits before it,
+        // which were lexed as a decimal number (or a number and a unit, as in `1Bh`)
+        let slice = &lexer.source()[spans.last().unwrap().start..lexer.span().end];
+        let number = default_width_integer(asm_integer(slice).unwrap(), slice)?;
+        *tokens.last_mut().unwrap() = Token::Literal(Value::from(number));
+      }
+      RawToken::Identifier
+        if adjacent
+          && si_multiplier(lexer.slice()).is_some()
+          && !has_radix_prefix(&lexer.source()[spans.last().unwrap().start..])
+          && matches!(tokens.last(), Some(Token::Value(v) | Token::Literal(v)) if v.is_raw()) =>
+      {
+        // a lowercase SI suffix (e.g. `10k`) scales the number by powers of 1000, unlike the
+        // uppercase size units which are powers of 1024
+        let start = spans.last().unwrap().start;
+        let slice = &lexer.source()[start..lexer.span().end];
+        if let Some(Token::Value(v) | Token::Literal(v)) = tokens.last_mut() {
+          v.number = apply_multiplier(v.number.clone(), si_multiplier(lexer.slice()).unwrap(), slice)?;
+        }
+      }
+      RawToken::Identifier
+        if adjacent && matches!(tokens.last(), Some(Token::Value(v) | Token::Literal(v)) if v.is_raw()) =>
+      {
+        // a type directly after a number (e.g. `5i8`) makes a typed literal, so that any
+        // unary sign before it applies to the already typed value
+        let value = match tokens.last() {
+          Some(Token::Value(v) | Token::Literal(v)) => cast_to(lexer.slice(), v.clone()),
+          _ => None,
+        };
+
+        match value {
+          Some(value) => *tokens.last_mut().unwrap() = Token::Value(value),
+          None => tokens.push(Token::Identifier(lexer.slice().to_string())),
+        }
+      }
       RawToken::Identifier => tokens.push(Token::Identifier(lexer.slice().to_string())),
       RawToken::Operator => {
-        match lexer.slice() {
+        let op = match lexer.slice() {
           // + and - are both binary and unary operators so look at the previous token
-          "+" | "-" => {
+          "+" | "-"
             if tokens.is_empty()
-              || matches!(tokens.last(), Some(t) if t.is_operator() || t.is_lparen() || t.is_newline())
-            {
-              tokens.push(Token::Operator(format!("{}u", lexer.slice())));
-              continue;
-            }
+              || matches!(tokens.last(), Some(t) if t.is_binary_op() || t.is_unary_op() || t.is_lparen() || t.is_comma() || t.is_newline()) =>
+          {
+            format!("{}u", lexer.slice())
           }
           // both ! and ~ are exclusively unary operators
-          "!" | "~" => {
-            tokens.push(Token::Operator(format!("{}u", lexer.slice())));
-            continue;
-          }
-          _ => (),
+          "!" | "~" => format!("{}u", lexer.slice()),
+          op => op.to_string(),
         };
 
-        tokens.push(Token::Operator(lexer.slice().to_string()));
+        tokens.push(Token::Operator(op));
       }
       RawToken::LParen => tokens.push(Token::LParen),
       RawToken::RParen => tokens.push(Token::RParen),
+      RawToken::Comma => tokens.push(Token::Comma),
       RawToken::Newline => tokens.push(Token::Newline),
       RawToken::Error => {
-        let slice = lexer.slice();
-        return Err(format!("Unexpected token in input '{}'", slice));
+        return Err(ClcError::Lex {
+          slice: lexer.slice().to_string(),
+          position: lexer.span().start,
+        });
       }
     }
+
+    // the token is either new or extends the last one (e.g. a unit or type suffix)
+    if spans.len() < tokens.len() {
+      spans.push(lexer.span());
+    } else if let Some(span) = spans.last_mut() {
+      span.end = lexer.span().end;
+    }
+  }
+
+  mark_percent(&mut tokens);
+  Ok(
+    tokens
+      .into_iter()
+      .zip(spans)
+      .map(|(token, span)| Spanned { token, span })
+      .collect(),
+  )
+}
+
+/// Turns a trailing `%` operator into the postfix percent operator.
+fn mark_percent(tokens: &mut [Token]) {
+  if let Some(token @ Token::Operator(_)) = tokens.last_mut() {
+    if matches!(token, Token::Operator(op) if op == "%") {
+      *token = Token::Operator("%p".to_string());
+    }
   }
-  return Ok(tokens);
 }
 
 #[cfg(test)]
 mod tests {
   use super::*;
+  use crate::value::Width;
 
   #[rustfmt::skip]
-  macro_rules! u64_t { ($value:literal) => { Token::from(Number::from($value as u64)) }; }
+  macro_rules! u64_t { ($value:literal) => { Token::Literal(Value::from(Number::from($value as u64))) }; }
   #[rustfmt::skip]
   macro_rules! f64_t { ($value:literal) => { Token::from(Number::from($value as f64)) }; }
   #[rustfmt::skip]
@@ -177,30 +483,137 @@ mod tests {
   #[rustfmt::skip]
   macro_rules! op_t { ($value:literal) => { Token::Operator($value.to_string()) }; }
 
+  /// Tokenizes the input without spans.
+  fn lex(input: &str) -> Result<Vec<Token>, ClcError> {
+    tokenize(input).map(|tokens| tokens.into_iter().map(|t| t.token).collect())
+  }
+
   #[test]
   fn test_tokenize_integer() {
     let input = "0x1F 0o777 0b1101 101";
     let expected = vec![u64_t!(0x1F), u64_t!(0o777), u64_t!(0b1101), u64_t!(101)];
 
-    let tokens = tokenize(input);
+    let tokens = lex(input);
     assert_eq!(tokens, Ok(expected));
   }
 
   #[test]
+  fn test_tokenize_wide_integer() {
+    let input = "0xFFFFFFFFFFFFFFFF";
+    assert_eq!(lex(input), Ok(vec![u64_t!(0xFFFFFFFFFFFFFFFF)]));
+
+    let input = "0xFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFF";
+    assert_eq!(
+      lex(input),
+      Ok(vec![Token::Literal(Value::from(Number::from(u128::MAX)))])
+    );
+
+    let input = "0x1FFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFF";
+    let expected = ClcError::IntegerOverflow {
+      slice: input.to_string(),
+      bits: 128,
+    };
+    assert_eq!(lex(input), Err(expected));
+  }
+
+  #[test]
+  #[allow(clippy::approx_constant)]
   fn test_tokenize_float() {
     let input = "3.141 0.0001 2. .5";
     let expected = vec![f64_t!(3.141), f64_t!(0.0001), f64_t!(2.), f64_t!(0.5)];
 
-    let tokens = tokenize(input);
+    let tokens = lex(input);
     assert_eq!(tokens, Ok(expected));
   }
 
+  #[test]
+  fn test_tokenize_hex_float() {
+    let input = "0x1.8p3 0x1p-1 0xA.8P0 0x.8p+1 0x1.p0";
+    let expected = vec![f64_t!(12.0), f64_t!(0.5), f64_t!(10.5), f64_t!(1.0), f64_t!(1.0)];
+    assert_eq!(lex(input), Ok(expected));
+
+    // the extremes of f64 are exact
+    let bits = |input| match &lex(input).unwrap()[..] {
+      [Token::Value(v)] => f64::from(v.number.clone()).to_bits(),
+      tokens => panic!("{:?}", tokens),
+    };
+    assert_eq!(bits("0x1.fffffffffffffp1023"), f64::MAX.to_bits());
+    assert_eq!(bits("0x1p-1074"), 1);
+    assert_eq!(bits("0x1p-1022"), f64::MIN_POSITIVE.to_bits());
+    assert_eq!(bits("0x0p99999"), 0);
+    assert_eq!(bits("0x1p99999"), f64::INFINITY.to_bits());
+    assert!(lex("0x1.8").is_err());
+  }
+
+  #[test]
+  fn test_tokenize_char() {
+    let input = r"'A' ' ' '\n' '\'' '\\'";
+    let expected = [65u64, 32, 10, 39, 92].map(|c| Token::Literal(Value::from(Number::from(c))));
+    assert_eq!(lex(input), Ok(expected.to_vec()));
+
+    for input in ["'é'", "'ab'", "''"] {
+      assert!(
+        matches!(lex(input), Err(ClcError::Lex { position: 0, .. })),
+        "{}",
+        input
+      );
+    }
+  }
+
+  #[test]
+  fn test_tokenize_ipv4() {
+    let input = "192.168.0.1 0.0.0.255 255.0.0.0";
+    let expected = [0xC0A80001u32, 0xFF, 0xFF000000].map(|c| Token::from(Number::from(c)));
+    assert_eq!(lex(input), Ok(expected.to_vec()));
+
+    let err = ClcError::Syntax("Invalid IPv4 address '1.2.3.256'".to_string());
+    assert_eq!(lex("1.2.3.256"), Err(err));
+  }
+
+  #[test]
+  fn test_tokenize_unit() {
+    let input = "10K 1.5 M 100°C\n-2°";
+    let expected = vec![
+      Token::Value(Value::new(Number::from(10u64), Unit::Kilobyte)),
+      Token::Value(Value::new(Number::from(1.5f64), Unit::Megabyte)),
+      Token::Value(Value::new(Number::from(100u64), Unit::Celsius)),
+      Token::Newline,
+      op_t!("-u"),
+      Token::Value(Value::new(Number::from(2u64), Unit::Celsius)),
+    ];
+
+    let tokens = lex(input);
+    assert_eq!(tokens, Ok(expected));
+
+    // sizes are normalized to bytes and temperatures to floats
+    let value = Value::new(Number::from(10u64), Unit::Kilobyte);
+    assert_eq!(value.number, Number::from(10240u64));
+    let value = Value::new(Number::from(100u64), Unit::Celsius);
+    assert!(value.number.is_float());
+
+    assert_eq!(
+      lex("K"),
+      Err(ClcError::Syntax("Expected number before unit".to_string()))
+    );
+
+    // seconds are only a unit directly after a number or as a conversion target
+    let expected = vec![
+      Token::Value(Value::new(Number::from(10u64), Unit::Second)),
+      id_t!("s"),
+      u64_t!(2),
+      id_t!("s"),
+      id_t!("to"),
+      id_t!("seconds"),
+    ];
+    assert_eq!(lex("10s s 2 s to s"), Ok(expected));
+  }
+
   #[test]
   fn test_tokenize_identifier() {
     let input = "sin cos PI U64_MAX";
     let expected = vec![id_t!("sin"), id_t!("cos"), id_t!("PI"), id_t!("U64_MAX")];
 
-    let tokens = tokenize(input);
+    let tokens = lex(input);
     assert_eq!(tokens, Ok(expected));
   }
 
@@ -219,10 +632,103 @@ mod tests {
       u64_t!(4),
     ];
 
-    let tokens = tokenize(input);
+    let tokens = lex(input);
     assert_eq!(tokens, Ok(expected));
   }
 
+  #[test]
+  fn test_tokenize_typed_literal() {
+    let input = "-5i8 +5u8 5 u8";
+    let expected = vec![
+      op_t!("-u"),
+      Token::Value(Value::new_integer(5, Width::I8)),
+      op_t!("+"),
+      Token::Value(Value::new_integer(5, Width::U8)),
+      u64_t!(5),
+      Token::Identifier("u8".to_string()),
+    ];
+
+    let tokens = lex(input);
+    assert_eq!(tokens, Ok(expected));
+  }
+
+  #[test]
+  fn test_tokenize_typed_literal_bases() {
+    let input = "-1i8 10u8 0xFFu16 0b11i32 255i16";
+    let expected = vec![
+      op_t!("-u"),
+      Token::Value(Value::new_integer(1, Width::I8)),
+      Token::Value(Value::new_integer(10, Width::U8)),
+      Token::Value(Value::new_integer(0xFF, Width::U16)),
+      Token::Value(Value::new_integer(0b11, Width::I32)),
+      Token::Value(Value::new_integer(255, Width::I16)),
+    ];
+
+    assert_eq!(lex(input), Ok(expected));
+  }
+
+  #[test]
+  fn test_tokenize_si_multiplier() {
+    let input = "10k 2.5m 2g 1t 10 k 10K";
+    let expected = vec![
+      u64_t!(10_000),
+      f64_t!(2_500_000.0),
+      u64_t!(2_000_000_000),
+      u64_t!(1_000_000_000_000),
+      u64_t!(10),
+      id_t!("k"),
+      Token::Value(Value::new(Number::from(10u64), Unit::Kilobyte)),
+    ];
+
+    assert_eq!(lex(input), Ok(expected));
+    let expected = Token::Literal(Value::from(Number::from(20_000_000_000_000_000_000u128)));
+    assert_eq!(lex("20000000t"), Ok(vec![expected]));
+    let expected = ClcError::IntegerOverflow {
+      slice: "400000000000000000000000000t".to_string(),
+      bits: 128,
+    };
+    assert_eq!(lex("400000000000000000000000000t"), Err(expected));
+    assert_eq!(lex("0x10k"), Ok(vec![u64_t!(0x10), id_t!("k")]));
+    assert_eq!(lex("0b1m"), Ok(vec![u64_t!(1), id_t!("m")]));
+  }
+
+  #[test]
+  fn test_tokenize_default_width() {
+    use crate::settings::{set_settings, Settings};
+
+    set_settings(Settings {
+      width: Some(Width::U8),
+      ..Settings::default()
+    });
+    assert_eq!(lex("255"), Ok(vec![Token::Literal(Value::new_integer(255, Width::U8))]));
+    let expected = ClcError::WidthOverflow {
+      slice: "300".to_string(),
+      width: Width::U8,
+    };
+    assert_eq!(
+      lex("300").map_err(|err| err.to_string()),
+      Err("300 does not fit in default width u8".to_string())
+    );
+    assert_eq!(lex("1 + 300"), Err(expected));
+    assert_eq!(
+      lex("300u16"),
+      Ok(vec![Token::Value(Value::new_integer(300, Width::U16))])
+    );
+
+    set_settings(Settings {
+      width: Some(Width::I16),
+      ..Settings::default()
+    });
+    assert_eq!(
+      lex("32767"),
+      Ok(vec![Token::Literal(Value::new_integer(32767, Width::I16))])
+    );
+    assert!(lex("32768").is_err());
+    assert!(lex("2k").is_ok());
+    assert!(lex("40k").is_err());
+    set_settings(Settings::default());
+  }
+
   #[test]
   fn test_tokenize_binary() {
     let input = "1+2 3-4 5*6 7/8";
@@ -241,7 +747,146 @@ mod tests {
       u64_t!(8),
     ];
 
-    let tokens = tokenize(input);
+    let tokens = lex(input);
     assert_eq!(tokens, Ok(expected));
   }
+
+  #[test]
+  fn test_tokenize_power() {
+    let input = "2**10 2*3";
+    let expected = vec![u64_t!(2), op_t!("**"), u64_t!(10), u64_t!(2), op_t!("*"), u64_t!(3)];
+
+    let tokens = lex(input);
+    assert_eq!(tokens, Ok(expected));
+  }
+
+  #[test]
+  fn test_tokenize_percent() {
+    let input = "50%\n10 % 3 (5%) 2% * 1\n10 % -3\n10% - 3 10%+3";
+    let expected = vec![
+      u64_t!(50),
+      op_t!("%p"),
+      Token::Newline,
+      u64_t!(10),
+      op_t!("%"),
+      u64_t!(3),
+      Token::LParen,
+      u64_t!(5),
+      op_t!("%p"),
+      Token::RParen,
+      u64_t!(2),
+      op_t!("%p"),
+      op_t!("*"),
+      u64_t!(1),
+      Token::Newline,
+      u64_t!(10),
+      op_t!("%"),
+      op_t!("-u"),
+      u64_t!(3),
+      Token::Newline,
+      u64_t!(10),
+      op_t!("%p"),
+      op_t!("-"),
+      u64_t!(3),
+      u64_t!(10),
+      op_t!("%p"),
+      op_t!("+"),
+      u64_t!(3),
+    ];
+
+    let tokens = lex(input);
+    assert_eq!(tokens, Ok(expected));
+  }
+
+  #[test]
+  fn test_tokenize_comments() {
+    let input = "1 + 2 # add them\n// a full line comment\n3 / 4 // divide";
+    let expected = vec![
+      u64_t!(1),
+      op_t!("+"),
+      u64_t!(2),
+      Token::Newline,
+      Token::Newline,
+      u64_t!(3),
+      op_t!("/"),
+      u64_t!(4),
+    ];
+
+    let tokens = lex(input);
+    assert_eq!(tokens, Ok(expected));
+  }
+
+  #[test]
+  fn test_tokenize_comma() {
+    let input = "bits(1, -2)";
+    let expected = vec![
+      id_t!("bits"),
+      Token::LParen,
+      u64_t!(1),
+      Token::Comma,
+      op_t!("-u"),
+      u64_t!(2),
+      Token::RParen,
+    ];
+
+    let tokens = lex(input);
+    assert_eq!(tokens, Ok(expected));
+  }
+
+  #[test]
+  fn test_tokenize_spans() {
+    let spans = tokenize("10K + -5i8")
+      .unwrap()
+      .into_iter()
+      .map(|t| t.span)
+      .collect::<Vec<_>>();
+    assert_eq!(spans, [0..3, 4..5, 6..7, 7..10]);
+
+    let err = ClcError::Lex {
+      slice: "$".to_string(),
+      position: 4,
+    };
+    assert_eq!(tokenize("1 + $"), Err(err.clone()));
+    assert_eq!(err.to_string(), "Unexpected token in input '$' at column 5");
+  }
+
+  #[test]
+  fn test_tokenize_asm_syntax() {
+    use crate::settings::{set_settings, Settings};
+
+    let err = ClcError::Lex {
+      slice: "$FF".to_string(),
+      position: 0,
+    };
+    assert_eq!(lex("$FF"), Err(err));
+    assert_eq!(lex("0FFh"), Ok(vec![u64_t!(0), id_t!("FFh")]));
+
+    set_settings(Settings {
+      asm_syntax: true,
+      ..Settings::default()
+    });
+    let input = "$FF 0FFh 10H 1Bh 1101b 0b1101 $ffu8 0x10";
+    let expected = vec![
+      u64_t!(0xFF),
+      u64_t!(0xFF),
+      u64_t!(0x10),
+      u64_t!(0x1B),
+      u64_t!(0b1101),
+      u64_t!(0b1101),
+      Token::Value(Value::new_integer(0xFF, Width::U8)),
+      u64_t!(0x10),
+    ];
+    assert_eq!(lex(input), Ok(expected));
+
+    // identifiers and units that aren't assembler-style literals are unchanged
+    let expected = vec![
+      u64_t!(12),
+      id_t!("b"),
+      id_t!("FFh"),
+      Token::Value(Value::new(Number::from(2u64), Unit::Byte)),
+    ];
+    assert_eq!(lex("12b FFh 2B"), Ok(expected));
+    assert!(lex("$").is_err());
+    set_settings(Settings::default());
+  }
 }
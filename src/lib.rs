@@ -0,0 +1,39 @@
+pub mod alfred;
+pub mod env;
+mod error;
+pub mod features;
+mod functions;
+mod lexer;
+mod number;
+pub mod output;
+mod parser;
+pub mod settings;
+mod tree;
+mod unit;
+mod value;
+
+pub use crate::error::ClcError;
+pub use crate::functions::{list_constants, list_functions};
+pub use crate::number::{Number, Width};
+pub use crate::tree::Node;
+pub use crate::unit::Unit;
+pub use crate::value::Value;
+
+use crate::lexer::tokenize;
+use crate::parser::{parse, parse_last, parse_tree};
+
+/// Evaluates a program and returns the value of its last expression.
+pub fn evaluate(input: &str) -> Result<Value, ClcError> {
+  parse(tokenize(input)?)
+}
+
+/// Evaluates a program and returns the value of its last expression, or `None` if it has no
+/// expressions.
+pub fn evaluate_last(input: &str) -> Result<Option<Value>, ClcError> {
+  parse_last(tokenize(input)?)
+}
+
+/// Parses a program into the expression tree of each of its expressions, without evaluating them.
+pub fn expression_trees(input: &str) -> Result<Vec<Node>, ClcError> {
+  parse_tree(tokenize(input)?)
+}
@@ -1,21 +1,32 @@
-mod alfred;
-mod functions;
-mod lexer;
-mod number;
-mod parser;
-mod unit;
-mod value;
-
-use crate::alfred::{alfred_error, alfred_result};
-use crate::lexer::tokenize;
-use crate::parser::parse;
-use clap::Parser;
+use clap::{Parser, ValueEnum};
+use clc::alfred::{alfred_error, alfred_result, DEFAULT_SUBTITLE};
+use clc::env::{set_variable, take_warnings};
+use clc::features::{version_json, VERSION};
+use clc::output::json_result;
+use clc::settings::{set_settings, Settings};
+use clc::{evaluate, evaluate_last, expression_trees};
+use clc::{list_constants, list_functions, Number, Unit, Value, Width};
 use std::fs::File;
 use std::io::{self, Read};
 use std::process;
 
+/// The size of the groups of binary digits.
+#[derive(ValueEnum, Clone, Copy, Debug)]
+enum BinaryGroup {
+  Nibble,
+  Byte,
+}
+
+/// What is printed for a program without expressions.
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq)]
+enum EmptyOutput {
+  Zero,
+  Nothing,
+  Error,
+}
+
 #[derive(Parser, Debug)]
-#[command(name = "clc", version = "1.1")]
+#[command(name = "clc", version = VERSION)]
 pub struct Opts {
   /// Read expression from file.
   #[arg(short, long)]
@@ -25,9 +36,150 @@ pub struct Opts {
   #[arg(short, long, conflicts_with = "file")]
   expr: Option<String>,
 
+  /// Value of the previous result, bound to `_`
+  #[arg(long, value_name = "VALUE", allow_hyphen_values = true)]
+  prev: Option<String>,
+
   /// Enables alfred JSON output
   #[arg(long)]
   alfred: bool,
+
+  /// Enables JSON output of the value and its representations
+  #[arg(long, conflicts_with = "alfred")]
+  json: bool,
+
+  /// Subtitle of alfred items, `{value}` is replaced with the value
+  #[arg(long, value_name = "TEMPLATE", default_value = DEFAULT_SUBTITLE)]
+  alfred_subtitle: String,
+
+  /// Units listed first in alfred items and `--conversions`, in order (e.g. `M,G`)
+  #[arg(long, value_name = "UNITS", value_delimiter = ',', value_parser = parse_unit)]
+  favorites: Vec<Unit>,
+
+  /// Print only the full-precision number, without its unit
+  #[arg(long, conflicts_with = "alfred")]
+  raw: bool,
+
+  /// Also show the value in the base unit of its group
+  #[arg(long)]
+  with_base: bool,
+
+  /// Show sizes in both binary (IEC) and decimal (SI) units
+  #[arg(long)]
+  dual_size: bool,
+
+  /// Show sizes in the largest unit they are at least one of (e.g. `3 G`)
+  #[arg(long, conflicts_with = "dual_size")]
+  human: bool,
+
+  /// Also copy the result to the clipboard
+  #[arg(long)]
+  copy: bool,
+
+  /// Subtraction that underflows an unsigned width gives a signed result
+  #[arg(long)]
+  signed_sub: bool,
+
+  /// Integer addition, subtraction and multiplication that overflows its width is an error
+  #[arg(long)]
+  checked: bool,
+
+  /// Integer arithmetic that overflows its width clamps to the width's minimum or maximum
+  #[arg(long, conflicts_with = "checked")]
+  saturating: bool,
+
+  /// Bare integers are arbitrary-precision, only typed literals and casts have a fixed width
+  #[arg(long)]
+  bigint: bool,
+
+  /// Print the expression tree of each expression instead of evaluating it
+  #[arg(long, conflicts_with_all = ["alfred", "json", "null_separated"])]
+  tree: bool,
+
+  /// Print each expression with normalized spacing and parentheses showing its precedence
+  #[arg(long, conflicts_with_all = ["alfred", "json", "null_separated", "tree"])]
+  canonicalize: bool,
+
+  /// Print notes about surprising evaluation semantics to stderr
+  #[arg(long)]
+  warn: bool,
+
+  /// Number of decimals shown for floats with a fraction [default: all]
+  #[arg(long, value_name = "N")]
+  precision: Option<usize>,
+
+  /// Show floats with the shortest representation that parses back to the same value
+  #[arg(long)]
+  roundtrip: bool,
+
+  /// Show hex digits in uppercase
+  #[arg(long)]
+  upper_hex: bool,
+
+  /// Width of bare integer literals, which must fit in it
+  #[arg(long, value_name = "TYPE", value_parser = parse_width)]
+  width: Option<Width>,
+
+  /// Also accept assembler-style integer literals (`$FF`, `0FFh` and `1101b`)
+  #[arg(long)]
+  asm_syntax: bool,
+
+  /// Separate binary digits into nibbles or bytes with `_`, showing leading zeros within the width
+  #[arg(long, value_name = "SIZE", num_args = 0..=1, default_missing_value = "nibble")]
+  group_binary: Option<BinaryGroup>,
+
+  /// Deepest nesting of parentheses and user function calls [default: 256 and 64]
+  #[arg(long, value_name = "N")]
+  max_depth: Option<usize>,
+
+  /// Seed for `rand()` and `randint()`, which are seeded from the clock otherwise
+  #[arg(long, value_name = "N")]
+  seed: Option<u64>,
+
+  /// Output of a program without expressions
+  #[arg(long, value_name = "MODE", default_value = "zero")]
+  on_empty: EmptyOutput,
+
+  /// Right-align text results in a field of N characters
+  #[arg(long, value_name = "N")]
+  field_width: Option<usize>,
+
+  /// Wrap results longer than N characters onto continuation lines
+  #[arg(long, value_name = "N", value_parser = clap::value_parser!(u64).range(2..))]
+  wrap: Option<u64>,
+
+  /// Evaluate NUL-separated records from stdin, printing NUL-separated results
+  #[arg(long)]
+  null_separated: bool,
+
+  /// Listen on a Unix socket, evaluating each line received and writing back its result
+  #[cfg(unix)]
+  #[arg(long, value_name = "PATH", conflicts_with_all = ["tree", "canonicalize", "null_separated", "file", "expr"])]
+  serve: Option<String>,
+
+  /// Print version and build info as JSON
+  #[arg(long)]
+  version_json: bool,
+
+  /// Print the names of all functions and aliases
+  #[arg(long)]
+  list_functions: bool,
+
+  /// Print the names of all constants
+  #[arg(long)]
+  list_constants: bool,
+
+  /// Print the conversion from a unit to each unit of its group
+  #[arg(long, value_name = "UNIT", value_parser = parse_unit)]
+  conversions: Option<Unit>,
+}
+
+fn parse_width(name: &str) -> Result<Width, String> {
+  Width::from_name(name).ok_or_else(|| format!("'{}' is not an integer type", name))
+}
+
+fn parse_unit(name: &str) -> Result<Unit, String> {
+  Unit::from_str(name).ok_or_else(|| format!("'{}' is not a unit", name))
 }
 
 fn read_input(opts: &Opts) -> String {
@@ -46,8 +198,13 @@ fn read_input(opts: &Opts) -> String {
   } else if opts.expr.is_some() {
     program = opts.expr.clone().unwrap();
   } else {
-    let stdin = io::stdin();
-    match stdin.read_line(&mut program) {
+    let mut stdin = io::stdin();
+    let result = if opts.null_separated {
+      stdin.read_to_string(&mut program)
+    } else {
+      stdin.read_line(&mut program)
+    };
+    match result {
       Ok(_) => (),
       Err(err) => {
         eprint!("{}", err);
@@ -55,7 +212,83 @@ fn read_input(opts: &Opts) -> String {
       }
     }
   }
-  return program;
+  program
+}
+
+/// Evaluates the program, returning `None` when it has no expressions and nothing should be printed.
+fn evaluate_program(program: &str, opts: &Opts) -> Result<Option<Value>, String> {
+  match (evaluate_last(program).map_err(|err| err.to_string())?, opts.on_empty) {
+    (Some(value), _) => Ok(Some(value)),
+    (None, EmptyOutput::Zero) => Ok(Some(Value::default())),
+    (None, EmptyOutput::Nothing) => Ok(None),
+    (None, EmptyOutput::Error) => Err("No expressions to evaluate".to_string()),
+  }
+}
+
+/// Evaluates each NUL-separated record as its own program, terminating each result with NUL.
+fn evaluate_records(input: &str, opts: &Opts) -> Result<String, String> {
+  input
+    .split_terminator('\0')
+    .map(|record| {
+      evaluate(record)
+        .map(|value| format!("{}\0", output_result(value, opts)))
+        .map_err(|err| err.to_string())
+    })
+    .collect()
+}
+
+/// Evaluates each line read from connections to the socket, one connection at a time, and writes
+/// back its result or error on a line. Functions defined by a line persist across connections.
+#[cfg(unix)]
+fn serve(path: &str, opts: &Opts) -> io::Result<()> {
+  use std::os::unix::fs::FileTypeExt;
+  use std::os::unix::net::UnixListener;
+
+  // a socket left behind by a previous server would make the bind fail
+  if std::fs::metadata(path).is_ok_and(|meta| meta.file_type().is_socket()) {
+    std::fs::remove_file(path)?;
+  }
+
+  let listener = UnixListener::bind(path)?;
+  for stream in listener.incoming() {
+    // an error on a connection (e.g. invalid UTF-8 or a client that left) only drops that one
+    if let Err(err) = stream.and_then(|stream| serve_connection(stream, opts)) {
+      eprintln!("{}: {}", path, err);
+    }
+  }
+  Ok(())
+}
+
+/// Evaluates each line read from a connection and writes back its result or error on a line.
+#[cfg(unix)]
+fn serve_connection(stream: std::os::unix::net::UnixStream, opts: &Opts) -> io::Result<()> {
+  use std::io::{BufRead, BufReader, Write};
+
+  let mut writer = stream.try_clone()?;
+  for line in BufReader::new(stream).lines() {
+    let result = match evaluate(&line?) {
+      Ok(value) => output_result(value, opts),
+      Err(err) => err.to_string(),
+    };
+    take_warnings();
+    writeln!(writer, "{}", result)?;
+  }
+  Ok(())
+}
+
+/// Binds the value given by `--prev` to `_`.
+fn bind_prev(opts: &Opts) -> Result<(), String> {
+  if let Some(prev) = &opts.prev {
+    let value = evaluate(prev).map_err(|err| format!("Invalid previous value: {}", err))?;
+    set_variable("_", value);
+  }
+  Ok(())
+}
+
+fn print_warnings() {
+  for warning in take_warnings() {
+    eprintln!("note: {}", warning);
+  }
 }
 
 fn output_err(err: String, opts: &Opts) {
@@ -66,28 +299,431 @@ fn output_err(err: String, opts: &Opts) {
   }
 }
 
+fn output_result(value: Value, opts: &Opts) -> String {
+  if opts.alfred {
+    alfred_result(value, &opts.alfred_subtitle, &opts.favorites)
+  } else if opts.json {
+    json_result(value)
+  } else {
+    // text results are right-aligned so that results line up in columns
+    let text = format_result(value, opts);
+    format!("{:>1$}", text, opts.field_width.unwrap_or(0))
+  }
+}
+
+fn format_result(value: Value, opts: &Opts) -> String {
+  if opts.raw {
+    value.number.to_string()
+  } else if opts.dual_size && value.unit.is_size() {
+    dual_size(value)
+  } else if opts.human && value.unit.is_size() {
+    human_size(value)
+  } else if opts.with_base && value.unit.base() != value.unit {
+    format!("{} ({} {})", value, value.number, value.unit.base().name())
+  } else {
+    format!("{}", value)
+  }
+}
+
+/// Formats a size with the largest fitting binary and decimal units (e.g. `9.31 GiB (10 GB)`).
+fn dual_size(value: Value) -> String {
+  let (iec, si) = (Unit::fit("size", &value.number), Unit::fit("si size", &value.number));
+  let iec_suffix = match iec {
+    Unit::Byte => iec.to_string(),
+    _ => format!("{}iB", iec),
+  };
+
+  format!(
+    "{} {} ({} {})",
+    Unit::specialize(value.number.clone(), iec).as_pretty_string(),
+    iec_suffix,
+    Unit::specialize(value.number, si).as_pretty_string(),
+    si
+  )
+}
+
+/// Formats a size with the largest unit of its group that it is at least one of (e.g. `1.50 K`).
+fn human_size(value: Value) -> String {
+  let unit = Unit::fit(value.unit.group(), &value.number);
+  format!("{} {}", Unit::specialize(value.number, unit).as_pretty_string(), unit)
+}
+
+/// Lists the formula converting `x` of a unit to each unit of its group (e.g. `K to B: x * 1024`).
+fn conversions(from: Unit, favorites: &[Unit]) -> String {
+  let lines = Unit::for_group_with_favorites(from.group(), favorites)
+    .into_iter()
+    .map(|to| {
+      let at = |x: f64| {
+        let value = Value::new(Number::from(x), from).convert(to).unwrap();
+        f64::from(Unit::specialize(value.number, to))
+      };
+      // every conversion is linear, with an offset for temperatures, and rounding to 12 significant
+      // digits hides the float error of subtracting the offset
+      let round = |v: f64| format!("{:.11e}", v).parse::<f64>().unwrap();
+      let (factor, offset) = (round(at(1.0) - at(0.0)), round(at(0.0)));
+      let mut formula = if factor == 1.0 {
+        "x".to_string()
+      } else {
+        format!("x * {}", factor)
+      };
+      if offset != 0.0 {
+        formula += &format!(" {} {}", if offset < 0.0 { '-' } else { '+' }, offset.abs());
+      }
+      format!("{} to {}: {}", from, to, formula)
+    });
+  lines.collect::<Vec<_>>().join("\n")
+}
+
+/// Wraps text onto lines of at most `width` characters, each continued line ending in `\`.
+fn wrap_output(text: &str, width: usize) -> String {
+  let chars = text.chars().collect::<Vec<_>>();
+  let mut rest = &chars[..];
+  let mut lines = vec![];
+
+  // each continued line keeps one character for the continuation marker
+  while rest.len() > width {
+    lines.push(format!("{}\\", rest[..width - 1].iter().collect::<String>()));
+    rest = &rest[width - 1..];
+  }
+  lines.push(rest.iter().collect());
+  lines.join("\n")
+}
+
+#[cfg(feature = "clipboard")]
+fn copy_to_clipboard(text: &str) -> Result<(), String> {
+  arboard::Clipboard::new()
+    .and_then(|mut clipboard| clipboard.set_text(text))
+    .map_err(|err| format!("Unable to copy to clipboard: {}", err))
+}
+
+#[cfg(not(feature = "clipboard"))]
+fn copy_to_clipboard(_: &str) -> Result<(), String> {
+  Err("Unable to copy to clipboard: clc was built without clipboard support".to_string())
+}
+
 fn main() {
   let opts = Opts::parse();
+  if opts.version_json {
+    println!("{}", version_json());
+    return;
+  } else if opts.list_functions {
+    println!("{}", list_functions());
+    return;
+  } else if opts.list_constants {
+    println!("{}", list_constants());
+    return;
+  } else if let Some(unit) = opts.conversions {
+    println!("{}", conversions(unit, &opts.favorites));
+    return;
+  }
+
+  set_settings(Settings {
+    signed_sub: opts.signed_sub,
+    checked: opts.checked,
+    saturating: opts.saturating,
+    bigint: opts.bigint,
+    warn: opts.warn,
+    precision: opts.precision,
+    roundtrip: opts.roundtrip,
+    upper_hex: opts.upper_hex,
+    width: opts.width,
+    asm_syntax: opts.asm_syntax,
+    binary_group: opts.group_binary.map(|group| match group {
+      BinaryGroup::Nibble => 4,
+      BinaryGroup::Byte => 8,
+    }),
+    seed: opts.seed,
+    max_depth: opts.max_depth,
+  });
+
+  if let Err(err) = bind_prev(&opts) {
+    output_err(err, &opts);
+    process::exit(1);
+  }
+
+  #[cfg(unix)]
+  if let Some(path) = &opts.serve {
+    if let Err(err) = serve(path, &opts) {
+      eprintln!("{}: {}", path, err);
+      process::exit(1);
+    }
+    return;
+  }
+
   let program = read_input(&opts);
-  let tokens = match tokenize(&program) {
-    Ok(tokens) => tokens,
+  if opts.tree || opts.canonicalize {
+    match expression_trees(&program) {
+      Ok(trees) if opts.canonicalize => trees.iter().for_each(|tree| println!("{}", tree.canonical())),
+      Ok(trees) => trees.iter().for_each(|tree| println!("{}", tree)),
+      Err(err) => {
+        output_err(err.to_string(), &opts);
+        process::exit(1);
+      }
+    }
+    return;
+  }
+
+  if opts.null_separated {
+    let results = evaluate_records(&program, &opts);
+    print_warnings();
+    match results {
+      Ok(results) => print!("{}", results),
+      Err(err) => {
+        output_err(err, &opts);
+        process::exit(1);
+      }
+    }
+    return;
+  }
+
+  let result = evaluate_program(&program, &opts);
+  print_warnings();
+  let result = match result {
+    Ok(Some(value)) => value,
+    Ok(None) => return,
     Err(err) => {
       output_err(err, &opts);
       process::exit(1);
     }
   };
 
-  let result = match parse(tokens) {
-    Ok(value) => value,
-    Err(err) => {
-      output_err(err, &opts);
+  let output = output_result(result.clone(), &opts);
+  match opts.wrap {
+    Some(width) if !opts.alfred && !opts.json => println!("{}", wrap_output(&output, width as usize)),
+    _ => println!("{}", output),
+  }
+  if opts.copy {
+    if let Err(err) = copy_to_clipboard(&result.to_string()) {
+      eprintln!("{}", err);
       process::exit(1);
     }
-  };
+  }
+}
 
-  if opts.alfred {
-    println!("{}", alfred_result(result));
-  } else {
-    println!("{}", result);
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_copy_output_unchanged() {
+    let value = evaluate("1 + 2").unwrap();
+    let opts = Opts::parse_from(["clc"]);
+    let copy_opts = Opts::parse_from(["clc", "--copy"]);
+    assert_eq!(output_result(value.clone(), &opts), output_result(value, &copy_opts));
+  }
+
+  #[test]
+  fn test_with_base_output() {
+    let opts = Opts::parse_from(["clc", "--with-base"]);
+    let value = evaluate("1.5K").unwrap();
+    assert_eq!(output_result(value, &opts), "1.5K (1536 bytes)");
+
+    let value = evaluate("1536B").unwrap();
+    assert_eq!(output_result(value, &opts), "1536B");
+  }
+
+  #[test]
+  fn test_raw_output() {
+    let opts = Opts::parse_from(["clc", "--raw"]);
+    let value = evaluate("1K").unwrap();
+    assert_eq!(output_result(value, &opts), "1024");
+
+    let value = evaluate("PI").unwrap();
+    assert_eq!(output_result(value, &opts), std::f64::consts::PI.to_string());
+    assert!(Opts::try_parse_from(["clc", "--raw", "--alfred"]).is_err());
+  }
+
+  #[test]
+  fn test_dual_size_output() {
+    let opts = Opts::parse_from(["clc", "--dual-size"]);
+    let value = evaluate("10000000000 bytes").unwrap();
+    assert_eq!(output_result(value, &opts), "9.313225746154785 GiB (10 GB)");
+
+    let value = evaluate("1023B").unwrap();
+    assert_eq!(output_result(value, &opts), "1023 B (1.023 KB)");
+
+    let value = evaluate("1.5").unwrap();
+    assert_eq!(output_result(value, &opts), "1.5");
+  }
+
+  #[test]
+  fn test_human_output() {
+    let opts = Opts::parse_from(["clc", "--human"]);
+    let value = evaluate("1023 bytes").unwrap();
+    assert_eq!(output_result(value, &opts), "1023 B");
+
+    let value = evaluate("1024 bytes").unwrap();
+    assert_eq!(output_result(value, &opts), "1 K");
+
+    let value = evaluate("3221225472 bytes").unwrap();
+    assert_eq!(output_result(value, &opts), "3 G");
+
+    let value = evaluate("1.5T to M").unwrap();
+    assert_eq!(output_result(value, &opts), "1.5 T");
+
+    let value = evaluate("2500000 bytes to KB").unwrap();
+    assert_eq!(output_result(value, &opts), "2.5 MB");
+
+    let value = evaluate("1536").unwrap();
+    assert_eq!(output_result(value, &opts), "1536");
+  }
+
+  #[test]
+  fn test_conversions() {
+    let opts = Opts::parse_from(["clc", "--conversions", "K"]);
+    let lines = conversions(opts.conversions.unwrap(), &opts.favorites);
+    let units = lines
+      .lines()
+      .map(|line| line.split(':').next().unwrap())
+      .collect::<Vec<_>>();
+    assert_eq!(units, ["K to B", "K to K", "K to M", "K to G", "K to T", "K to P"]);
+    assert!(lines.starts_with("K to B: x * 1024\nK to K: x\nK to M: x * 0.0009765625\n"));
+
+    let lines = conversions(Unit::Celsius, &[Unit::Fahrenheit]);
+    assert_eq!(lines, "°C to °F: x * 1.8 + 32\n°C to °C: x\n°C to °K: x + 273.15");
+    assert!(Opts::try_parse_from(["clc", "--conversions", "X"]).is_err());
+  }
+
+  #[test]
+  fn test_prev_value() {
+    bind_prev(&Opts::parse_from(["clc", "--prev", "21"])).unwrap();
+    assert_eq!(evaluate("_ * 2"), Ok(Value::new_integer(42, Width::U64)));
+
+    bind_prev(&Opts::parse_from(["clc", "--prev", "-1i8"])).unwrap();
+    assert_eq!(evaluate("_ + 1"), Ok(Value::new_integer(0, Width::I8)));
+    assert_eq!(format!("{}", evaluate("_").unwrap()), "-1");
+
+    let opts = Opts::parse_from(["clc", "--prev", "1 +"]);
+    assert_eq!(
+      bind_prev(&opts),
+      Err("Invalid previous value: Expected two arguments to +".to_string())
+    );
+  }
+
+  #[test]
+  fn test_field_width_output() {
+    let opts = Opts::parse_from(["clc", "--field-width", "8"]);
+    assert_eq!(output_result(evaluate("42").unwrap(), &opts), "      42");
+    assert_eq!(output_result(evaluate("1.5K").unwrap(), &opts), "    1.5K");
+    assert_eq!(output_result(evaluate("123456789").unwrap(), &opts), "123456789");
+
+    let opts = Opts::parse_from(["clc", "--field-width", "8", "--raw"]);
+    assert_eq!(output_result(evaluate("1K").unwrap(), &opts), "    1024");
+    let opts = Opts::parse_from(["clc", "--field-width", "8", "--json"]);
+    assert!(output_result(evaluate("1").unwrap(), &opts).starts_with('{'));
+  }
+
+  #[test]
+  fn test_on_empty() {
+    for program in ["", " \t\n", "# nothing"] {
+      let opts = Opts::parse_from(["clc"]);
+      assert_eq!(
+        evaluate_program(program, &opts),
+        Ok(Some(Value::new_integer(0, Width::U64)))
+      );
+      let opts = Opts::parse_from(["clc", "--on-empty", "zero"]);
+      assert_eq!(
+        evaluate_program(program, &opts),
+        Ok(Some(Value::new_integer(0, Width::U64)))
+      );
+      let opts = Opts::parse_from(["clc", "--on-empty", "nothing"]);
+      assert_eq!(evaluate_program(program, &opts), Ok(None));
+      let opts = Opts::parse_from(["clc", "--on-empty", "error"]);
+      assert_eq!(
+        evaluate_program(program, &opts),
+        Err("No expressions to evaluate".to_string())
+      );
+    }
+
+    // a result of 0 is still printed
+    let opts = Opts::parse_from(["clc", "--on-empty", "nothing"]);
+    assert_eq!(
+      evaluate_program("1 - 1", &opts),
+      Ok(Some(Value::new_integer(0, Width::U64)))
+    );
+    let opts = Opts::parse_from(["clc", "--on-empty", "error"]);
+    assert_eq!(
+      evaluate_program("\n0\n", &opts),
+      Ok(Some(Value::new_integer(0, Width::U64)))
+    );
+    assert_eq!(
+      evaluate_program("1 +", &opts),
+      Err("Expected two arguments to +".to_string())
+    );
+  }
+
+  #[test]
+  fn test_null_separated_records() {
+    let opts = Opts::parse_from(["clc", "--null-separated"]);
+    assert_eq!(
+      evaluate_records("1 + 1\x00u8(256)\x00", &opts),
+      Ok("2\x000\x00".to_string())
+    );
+    assert_eq!(evaluate_records("1 + 1\n\x001K", &opts), Ok("2\x001K\x00".to_string()));
+    assert!(evaluate_records("1\x00sin(\x00", &opts).is_err());
+  }
+
+  #[cfg(unix)]
+  #[test]
+  fn test_serve() {
+    use std::io::{BufRead, BufReader, Write};
+    use std::os::unix::net::UnixStream;
+
+    let path = std::env::temp_dir().join(format!("clc-test-{}.sock", process::id()));
+    let path = path.to_str().unwrap().to_string();
+    let server_path = path.clone();
+    std::thread::spawn(move || serve(&server_path, &Opts::parse_from(["clc"])));
+
+    let connect = || loop {
+      match UnixStream::connect(&path) {
+        Ok(stream) => return stream,
+        Err(_) => std::thread::sleep(std::time::Duration::from_millis(10)),
+      }
+    };
+    let request = |stream: &mut UnixStream, line: &str| {
+      writeln!(stream, "{}", line).unwrap();
+      let mut result = String::new();
+      BufReader::new(stream.try_clone().unwrap())
+        .read_line(&mut result)
+        .unwrap();
+      result
+    };
+
+    let mut stream = connect();
+    assert_eq!(request(&mut stream, "1 + 2"), "3\n");
+    assert_eq!(
+      request(&mut stream, "sin("),
+      "Encountered '(' without matching ')' at column 4\n"
+    );
+    assert_eq!(request(&mut stream, "f(x) = x * 2"), "0\n");
+    drop(stream);
+
+    // connections that send invalid UTF-8 or leave before their results are written are dropped
+    // without stopping the server
+    let mut stream = connect();
+    stream.write_all(b"1 + \xff\xfe\n").unwrap();
+    drop(stream);
+    let mut stream = connect();
+    writeln!(stream, "1\n2\n3").unwrap();
+    drop(stream);
+
+    // a function defined by a previous connection is still defined
+    let mut stream = connect();
+    assert_eq!(request(&mut stream, "f(21)"), "42\n");
+    std::fs::remove_file(&path).unwrap();
+  }
+
+  #[test]
+  fn test_wrap_output() {
+    let value = evaluate("U64_MAX").unwrap();
+    let binary = format!("{:#b}", value.number);
+    let wrapped = wrap_output(&binary, 32);
+
+    let lines = wrapped.lines().collect::<Vec<_>>();
+    assert_eq!(lines.len(), 3);
+    assert!(lines.iter().all(|line| line.len() <= 32));
+    assert!(lines[..2].iter().all(|line| line.ends_with('\\')));
+    assert_eq!(wrapped.replace("\\\n", ""), binary);
+    assert_eq!(wrap_output("0xff", 32), "0xff");
   }
 }
@@ -1,11 +1,16 @@
+use crate::settings::settings;
 use float_cmp::approx_eq;
+use num_bigint::BigInt;
+use num_traits::{Signed, ToPrimitive, Zero};
 use std::cmp::Ordering;
+use std::net::Ipv4Addr;
 use std::{fmt, fmt::Display};
 
-/// A number that is either a fixed-width integer or a float.
-#[derive(Debug, Copy, Clone)]
+/// A number that is either a fixed-width integer, an arbitrary-precision integer or a float.
+#[derive(Debug, Clone)]
 pub enum Number {
-  Integer(u64, Width),
+  Integer(u128, Width),
+  Big(BigInt),
   Float(f64),
 }
 
@@ -14,10 +19,12 @@ pub enum Number {
 macro_rules! number_cast {
   ($v: expr, $w: expr, $t: tt) => {
     match $w {
+      Width::U128 => ($v as u128) as $t,
       Width::U64 => ($v as u64) as $t,
       Width::U32 => ($v as u32) as $t,
       Width::U16 => ($v as u16) as $t,
       Width::U8 => ($v as u8) as $t,
+      Width::I128 => ($v as i128) as $t,
       Width::I64 => ($v as i64) as $t,
       Width::I32 => ($v as i32) as $t,
       Width::I16 => ($v as i16) as $t,
@@ -26,13 +33,33 @@ macro_rules! number_cast {
   };
 }
 
+/// Applies an integer method to two values as the type of the given width.
+macro_rules! integer_op {
+  ($v1: expr, $v2: expr, $w: expr, $method: tt) => {
+    match $w {
+      Width::U128 => ($v1 as u128).$method($v2 as u128),
+      Width::U64 => ($v1 as u64).$method($v2 as u64) as u128,
+      Width::U32 => ($v1 as u32).$method($v2 as u32) as u128,
+      Width::U16 => ($v1 as u16).$method($v2 as u16) as u128,
+      Width::U8 => ($v1 as u8).$method($v2 as u8) as u128,
+      Width::I128 => ($v1 as i128).$method($v2 as i128) as u128,
+      Width::I64 => ($v1 as i64).$method($v2 as i64) as u128,
+      Width::I32 => ($v1 as i32).$method($v2 as i32) as u128,
+      Width::I16 => ($v1 as i16).$method($v2 as i16) as u128,
+      Width::I8 => ($v1 as i8).$method($v2 as i8) as u128,
+    }
+  };
+}
+
 macro_rules! number_fmt {
   ($v: expr, $w: expr, $f: expr) => {
     match $w {
+      Width::U128 => format!($f, $v as u128),
       Width::U64 => format!($f, $v as u64),
       Width::U32 => format!($f, $v as u32),
       Width::U16 => format!($f, $v as u16),
       Width::U8 => format!($f, $v as u8),
+      Width::I128 => format!($f, $v as i128),
       Width::I64 => format!($f, $v as i64),
       Width::I32 => format!($f, $v as i32),
       Width::I16 => format!($f, $v as i16),
@@ -44,10 +71,12 @@ macro_rules! number_fmt {
 macro_rules! integer_cmp {
   ($v1: expr, $v2: expr, $w: expr) => {
     match $w {
+      Width::U128 => (($v1 as u128).cmp(&($v2 as u128))),
       Width::U64 => (($v1 as u64).cmp(&($v2 as u64))),
       Width::U32 => (($v1 as u32).cmp(&($v2 as u32))),
       Width::U16 => (($v1 as u16).cmp(&($v2 as u16))),
       Width::U8 => (($v1 as u8).cmp(&($v2 as u8))),
+      Width::I128 => (($v1 as i128).cmp(&($v2 as i128))),
       Width::I64 => (($v1 as i64).cmp(&($v2 as i64))),
       Width::I32 => (($v1 as i32).cmp(&($v2 as i32))),
       Width::I16 => (($v1 as i16).cmp(&($v2 as i16))),
@@ -57,19 +86,26 @@ macro_rules! integer_cmp {
 }
 
 macro_rules! impl_arithmetic_op {
-  ($ops: tt, $func: tt, $op: tt) => {
+  ($ops: tt, $func: tt, $op: tt, $wrapping: tt, $saturating: tt) => {
     impl std::ops::$ops<Number> for Number {
       type Output = Number;
       fn $func(self, rhs: Number) -> Number {
         match self {
-          Number::Integer(v1, w) => match rhs {
-            Number::Integer(v2, _) => Number::new_integer(v1 $op w.mask(v2), w),
-            Number::Float(v2) => Number::new_integer(v1 $op w.mask(v2 as u64), w),
-          },
-          Number::Float(v1) => match rhs {
-            Number::Integer(v2, w) => Number::new_float(v1 $op number_cast!(v2, w, f64)),
-            Number::Float(v2) => Number::new_float(v1 $op v2),
-          },
+          Number::Integer(v1, w) => {
+            let (v1, v2, w) = match rhs {
+              // both operands are extended to the promoted width before the operation
+              Number::Integer(v2, w2) => (number_cast!(v1, w, u128), number_cast!(v2, w2, u128), w.promote(w2)),
+              Number::Big(v2) => (v1, w.mask(wrap_big(&v2)), w),
+              Number::Float(v2) => (v1, number_cast!(v2, w, u128), w),
+            };
+            if settings().saturating {
+              Number::new_integer(integer_op!(v1, v2, w, $saturating), w)
+            } else {
+              Number::new_integer(integer_op!(v1, v2, w, $wrapping), w)
+            }
+          }
+          Number::Big(v1) => Number::Big(v1 $op BigInt::from(rhs)),
+          Number::Float(v1) => Number::new_float(v1 $op f64::from(rhs)),
         }
       }
     }
@@ -77,19 +113,18 @@ macro_rules! impl_arithmetic_op {
 }
 
 macro_rules! impl_bitwise_op {
-  ($ops: tt, $func: tt, $op: tt) => {
+  ($ops: tt, $func: tt, $op: tt, $big_rhs: ty) => {
     impl std::ops::$ops<Number> for Number {
       type Output = Number;
       fn $func(self, rhs: Number) -> Number {
         match self {
           Number::Integer(v1, w) => match rhs {
             Number::Integer(v2, _) => Number::new_integer(v1 $op v2, w),
-            Number::Float(v2) => Number::new_integer(v1 $op w.mask(v2 as u64), w),
-          },
-          Number::Float(_) => match rhs {
-            Number::Integer(_, _) => Number::new_float(f64::NAN),
-            Number::Float(_) => Number::new_float(f64::NAN),
+            Number::Big(v2) => Number::new_integer(v1 $op wrap_big(&v2), w),
+            Number::Float(v2) => Number::new_integer(v1 $op w.mask(v2 as u128), w),
           },
+          Number::Big(v1) => Number::Big(v1 $op <$big_rhs>::from(rhs)),
+          Number::Float(_) => Number::new_float(f64::NAN),
         }
       }
     }
@@ -107,7 +142,7 @@ macro_rules! impl_number_from {
   ($t: tt, $w: expr) => {
     impl From<$t> for Number {
       fn from(v: $t) -> Self {
-        Number::new_integer(v as u64, $w)
+        Number::new_integer(v as u128, $w)
       }
     }
   };
@@ -119,6 +154,7 @@ macro_rules! impl_from_number {
       fn from(src: Number) -> $t {
         match src {
           Number::Integer(v, w) => number_cast!(v, w, $t),
+          Number::Big(v) => wrap_big(&v) as $t,
           Number::Float(v) => v as $t,
         }
       }
@@ -127,11 +163,13 @@ macro_rules! impl_from_number {
 }
 
 // `From` traits
+impl_number_from!(u128, Width::U128);
 impl_number_from!(u64, Width::U64);
 impl_number_from!(u32, Width::U32);
 impl_number_from!(u16, Width::U16);
 impl_number_from!(u8, Width::U8);
 
+impl_number_from!(i128, Width::I128);
 impl_number_from!(i64, Width::I64);
 impl_number_from!(i32, Width::I32);
 impl_number_from!(i16, Width::I16);
@@ -139,45 +177,162 @@ impl_number_from!(i8, Width::I8);
 
 impl_number_from!(bool, Width::U8);
 impl_number_from!(f64);
+impl_number_from!(f32);
 
+impl_from_number!(u128);
 impl_from_number!(u64);
 impl_from_number!(u32);
 impl_from_number!(u16);
 impl_from_number!(u8);
 
+impl_from_number!(i128);
 impl_from_number!(i64);
 impl_from_number!(i32);
 impl_from_number!(i16);
 impl_from_number!(i8);
 
-impl_from_number!(f64);
+impl From<BigInt> for Number {
+  fn from(v: BigInt) -> Self {
+    Number::Big(v)
+  }
+}
+
+impl From<Number> for f64 {
+  fn from(src: Number) -> f64 {
+    match src {
+      Number::Integer(v, w) => number_cast!(v, w, f64),
+      Number::Big(v) => v.to_f64().unwrap_or(f64::NAN),
+      Number::Float(v) => v,
+    }
+  }
+}
+
+impl From<Number> for f32 {
+  fn from(src: Number) -> f32 {
+    f64::from(src) as f32
+  }
+}
+
+impl From<Number> for BigInt {
+  fn from(src: Number) -> BigInt {
+    match src {
+      Number::Integer(v, w) if w.is_signed() => BigInt::from(number_cast!(v, w, i128)),
+      Number::Integer(v, _) => BigInt::from(v),
+      Number::Big(v) => v,
+      Number::Float(v) => BigInt::from(v as i64),
+    }
+  }
+}
 
 impl From<Number> for bool {
   fn from(src: Number) -> bool {
     match src {
       Number::Integer(v, _) => v != 0,
+      Number::Big(v) => !v.is_zero(),
       Number::Float(v) => v != 0f64,
     }
   }
 }
 
-impl_arithmetic_op!(Add, add, +);
-impl_arithmetic_op!(Sub, sub, -);
-impl_arithmetic_op!(Mul, mul, *);
-impl_arithmetic_op!(Div, div, /);
-impl_arithmetic_op!(Rem, rem, %);
+/// Returns the low 128 bits of a big integer in two's complement.
+fn wrap_big(v: &BigInt) -> u128 {
+  let mut digits = v.magnitude().iter_u64_digits();
+  let low = digits.next().unwrap_or(0) as u128 | (digits.next().unwrap_or(0) as u128) << 64;
+  if v.is_negative() {
+    low.wrapping_neg()
+  } else {
+    low
+  }
+}
 
-impl_bitwise_op!(BitAnd, bitand, &);
-impl_bitwise_op!(BitOr, bitor, |);
-impl_bitwise_op!(BitXor, bitxor, ^);
-impl_bitwise_op!(Shl, shl, <<);
-impl_bitwise_op!(Shr, shr, >>);
+/// Returns the integer `k`-th root of `n`, rounded down.
+fn iroot(n: u128, k: u32) -> u128 {
+  if n < 2 || k == 1 {
+    return n;
+  }
+  // Newton's method decreases from an overestimate until it reaches the root, without the
+  // rounding of a float estimate (which is off by thousands for 128-bit values)
+  let k = k as u128;
+  let mut x = 1u128 << (128 - n.leading_zeros()).div_ceil(k as u32);
+  loop {
+    // a power that overflows is larger than `n`, so the quotient is zero
+    let quotient = x.checked_pow(k as u32 - 1).map_or(0, |p| n / p);
+    let next = ((k - 1) * x + quotient) / k;
+    if next >= x {
+      return x;
+    }
+    x = next;
+  }
+}
+
+/// Separates digits into groups of `size` from the right with `_`.
+fn group_digits(digits: &str, size: usize) -> String {
+  let chars = digits.chars().collect::<Vec<_>>();
+  let groups = chars.rchunks(size).rev().map(|group| group.iter().collect::<String>());
+  groups.collect::<Vec<_>>().join("_")
+}
+
+/// Compares two floats, treating approximately equal values as equal.
+fn float_cmp(v1: f64, v2: f64) -> Ordering {
+  if approx_eq!(f64, v1, v2) {
+    Ordering::Equal
+  } else if v1 < v2 {
+    Ordering::Less
+  } else {
+    Ordering::Greater
+  }
+}
+
+impl_arithmetic_op!(Add, add, +, wrapping_add, saturating_add);
+impl_arithmetic_op!(Sub, sub, -, wrapping_sub, saturating_sub);
+impl_arithmetic_op!(Mul, mul, *, wrapping_mul, saturating_mul);
+impl_arithmetic_op!(Div, div, /, wrapping_div, saturating_div);
+// a remainder can't overflow except for MIN % -1, which is 0 either way
+impl_arithmetic_op!(Rem, rem, %, wrapping_rem, wrapping_rem);
+
+impl_bitwise_op!(BitAnd, bitand, &, BigInt);
+impl_bitwise_op!(BitOr, bitor, |, BigInt);
+impl_bitwise_op!(BitXor, bitxor, ^, BigInt);
+/// Returns the count of a shift, where a count that doesn't fit in a `u32` (including a negative
+/// count) shifts out every bit.
+fn shift_count(rhs: Number) -> u32 {
+  u32::try_from(u128::from(rhs)).unwrap_or(u32::MAX)
+}
+
+impl std::ops::Shl<Number> for Number {
+  type Output = Number;
+  fn shl(self, rhs: Number) -> Number {
+    match self {
+      // shifting by the width or more shifts out every bit
+      Number::Integer(v1, w) => Number::new_integer(v1.checked_shl(shift_count(rhs)).unwrap_or(0), w),
+      Number::Big(v1) => Number::Big(v1 << shift_count(rhs)),
+      Number::Float(_) => Number::new_float(f64::NAN),
+    }
+  }
+}
+
+impl std::ops::Shr<Number> for Number {
+  type Output = Number;
+  fn shr(self, rhs: Number) -> Number {
+    match self {
+      // signed widths shift arithmetically, so shifting by the width or more leaves only the sign
+      Number::Integer(v1, w) if w.is_signed() => {
+        let shift = shift_count(rhs).min(w.bits() - 1);
+        Number::new_integer((number_cast!(v1, w, i128) >> shift) as u128, w)
+      }
+      Number::Integer(v1, w) => Number::new_integer(v1.checked_shr(shift_count(rhs)).unwrap_or(0), w),
+      Number::Big(v1) => Number::Big(v1 >> shift_count(rhs)),
+      Number::Float(_) => Number::new_float(f64::NAN),
+    }
+  }
+}
 
 impl std::ops::Neg for Number {
   type Output = Number;
   fn neg(self) -> Number {
     match self {
       Number::Integer(v, w) => Number::new_integer(v.wrapping_neg(), w),
+      Number::Big(v) => Number::Big(-v),
       Number::Float(v) => Number::new_float(-v),
     }
   }
@@ -187,7 +342,8 @@ impl std::ops::Not for Number {
   fn not(self) -> Number {
     match self {
       Number::Integer(v, w) => Number::new_integer(!v, w),
-      Number::Float(v) => Number::new_integer((v != 0f64) as u64, Width::U8),
+      Number::Big(v) => Number::Big(!v),
+      Number::Float(v) => Number::new_integer((v != 0f64) as u128, Width::U8),
     }
   }
 }
@@ -197,32 +353,14 @@ impl Eq for Number {}
 
 impl Ord for Number {
   fn cmp(&self, other: &Self) -> Ordering {
-    match self {
-      Number::Integer(v1, w) => match other {
-        Number::Integer(v2, _) => integer_cmp!(*v1, *v2, w),
-        Number::Float(v2) => integer_cmp!(*v1, *v2, w),
-      },
-      Number::Float(v1) => match other {
-        Number::Integer(v2, w) => {
-          let vf = number_cast!(*v2, w, f64);
-          if approx_eq!(f64, *v1, vf) {
-            Ordering::Equal
-          } else if v1 < &vf {
-            Ordering::Less
-          } else {
-            Ordering::Greater
-          }
-        }
-        Number::Float(v2) => {
-          if approx_eq!(f64, *v1, *v2) {
-            Ordering::Equal
-          } else if v1 < &v2 {
-            Ordering::Less
-          } else {
-            Ordering::Greater
-          }
-        }
-      },
+    match (self, other) {
+      (Number::Integer(v1, w), Number::Integer(v2, _)) => integer_cmp!(*v1, *v2, w),
+      (Number::Integer(v1, w), Number::Float(v2)) => integer_cmp!(*v1, *v2, w),
+      (Number::Float(_), _) | (Number::Big(_), Number::Float(_)) => {
+        float_cmp(f64::from(self.clone()), f64::from(other.clone()))
+      }
+      // any other comparison involves a big integer
+      _ => BigInt::from(self.clone()).cmp(&BigInt::from(other.clone())),
     }
   }
 }
@@ -239,8 +377,9 @@ impl PartialOrd<Number> for Number {
   }
 }
 
+#[allow(clippy::wrong_self_convention)]
 impl Number {
-  pub const fn new_integer(v: u64, w: Width) -> Number {
+  pub const fn new_integer(v: u128, w: Width) -> Number {
     Number::Integer(w.mask(v), w)
   }
 
@@ -249,18 +388,35 @@ impl Number {
   }
 
   pub fn is_integer(&self) -> bool {
-    matches!(self, Number::Integer(_, _))
+    matches!(self, Number::Integer(_, _) | Number::Big(_))
+  }
+
+  pub fn is_big(&self) -> bool {
+    matches!(self, Number::Big(_))
   }
 
   pub fn is_float(&self) -> bool {
     matches!(self, Number::Float(_))
   }
 
+  /// Returns the width of an integer, or `I64` for big integers and `U64` for floats.
+  pub fn width(&self) -> Width {
+    match self {
+      Number::Integer(_, w) => *w,
+      Number::Big(_) => Width::I64,
+      Number::Float(_) => Width::U64,
+    }
+  }
+
+  pub fn is_unsigned(&self) -> bool {
+    matches!(self, Number::Integer(_, w) if !w.is_signed())
+  }
+
   pub fn abs(&self) -> Number {
     match self {
       Number::Integer(v, w) => match w {
-        Width::I64 | Width::I32 | Width::I16 | Width::I8 => {
-          if self < &Number::from(0) {
+        Width::I128 | Width::I64 | Width::I32 | Width::I16 | Width::I8 => {
+          if *self < Number::from(0) {
             Number::new_integer(v.wrapping_neg(), *w)
           } else {
             Number::new_integer(*v, *w)
@@ -268,38 +424,363 @@ impl Number {
         }
         _ => Number::new_integer(*v, *w),
       },
+      Number::Big(v) => Number::Big(v.abs()),
       Number::Float(v) => Number::new_float(v.abs()),
     }
   }
 
-  pub fn pow(&self, other: &Number) -> Number {
-    let exp = u32::from((*other).abs());
+  /// Returns the magnitude of the number as an unsigned integer (floats are truncated).
+  pub fn magnitude(&self) -> u128 {
     match self {
-      Number::Integer(v, w) => match w {
-        Width::U64 => Number::new_integer(v.pow(exp), Width::U64),
-        Width::U32 => Number::new_integer((*v as u32).pow(exp) as u64, Width::U32),
-        Width::U16 => Number::new_integer((*v as u16).pow(exp) as u64, Width::U16),
-        Width::U8 => Number::new_integer((*v as u8).pow(exp) as u64, Width::U8),
-        Width::I64 => Number::new_integer((*v as i64).pow(exp) as u64, Width::I64),
-        Width::I32 => Number::new_integer((*v as i32).pow(exp) as u64, Width::I32),
-        Width::I16 => Number::new_integer((*v as i16).pow(exp) as u64, Width::I16),
-        Width::I8 => Number::new_integer((*v as i8).pow(exp) as u64, Width::I8),
-      },
-      Number::Float(v) => Number::new_float(v.powf(f64::from(*other))),
+      Number::Integer(v, w) if w.is_signed() => number_cast!(*v, w, i128).unsigned_abs(),
+      Number::Integer(v, _) => *v,
+      Number::Big(v) => v.magnitude().to_u128().unwrap_or(u128::MAX),
+      Number::Float(v) => v.abs() as u128,
+    }
+  }
+
+  /// Returns the sum of the decimal digits of the number's magnitude.
+  pub fn digit_sum(&self) -> Number {
+    let mut n = self.magnitude();
+    let mut sum = 0;
+    while n > 0 {
+      sum += n % 10;
+      n /= 10;
+    }
+    Number::new_integer(sum, self.width())
+  }
+
+  /// Returns the ASCII character with the number as its code point, or `None` if it isn't one.
+  pub fn to_ascii(&self) -> Option<char> {
+    if self.is_float() {
+      return None;
+    }
+    u8::try_from(BigInt::from(self.clone()))
+      .ok()
+      .filter(u8::is_ascii)
+      .map(char::from)
+  }
+
+  /// Returns the IPv4 address the number is packed as, or `None` if it doesn't fit in a `u32`.
+  /// The most significant byte is the first octet (network byte order).
+  pub fn to_ipv4(&self) -> Option<Ipv4Addr> {
+    if self.is_float() {
+      return None;
+    }
+    u32::try_from(BigInt::from(self.clone())).ok().map(Ipv4Addr::from)
+  }
+
+  /// Returns the number of decimal digits of the number's magnitude, which is 1 for zero.
+  pub fn digit_count(&self) -> Number {
+    let mut n = self.magnitude() / 10;
+    let mut count = 1;
+    while n > 0 {
+      count += 1;
+      n /= 10;
+    }
+    Number::new_integer(count, self.width())
+  }
+
+  /// Returns the digital root (the repeated digit sum) of the number's magnitude.
+  pub fn digital_root(&self) -> Number {
+    let n = self.magnitude();
+    let root = if n == 0 { 0 } else { 1 + (n - 1) % 9 };
+    Number::new_integer(root, self.width())
+  }
+
+  /// Returns the integer square root of the number's magnitude, rounded down.
+  pub fn isqrt(&self) -> Number {
+    match self {
+      Number::Integer(_, w) => Number::new_integer(iroot(self.magnitude(), 2), *w),
+      Number::Big(v) => Number::Big(v.abs().sqrt()),
+      Number::Float(v) => Number::new_float(v.abs().sqrt().floor()),
+    }
+  }
+
+  /// Returns the position of the highest set bit (the base 2 logarithm rounded down), or `None`
+  /// if the number isn't positive.
+  pub fn ilog2(&self) -> Option<Number> {
+    if *self <= Number::from(0i64) {
+      return None;
+    }
+    match self {
+      Number::Integer(v, w) => Some(Number::new_integer(v.ilog2() as u128, *w)),
+      Number::Big(v) => Some(Number::Big(BigInt::from(v.bits() - 1))),
+      Number::Float(v) => Some(Number::new_float(v.log2().floor())),
+    }
+  }
+
+  /// Returns whether the number is the square of an integer.
+  pub fn is_square(&self) -> bool {
+    if !self.is_whole() || *self < Number::from(0i64) {
+      return false;
+    }
+    let root = self.isqrt();
+    root.clone() * root == *self
+  }
+
+  /// Returns whether the number is the `k`-th power of an integer.
+  pub fn is_power(&self, k: u32) -> bool {
+    let negative = *self < Number::from(0i64);
+    if !self.is_whole() || k == 0 || (negative && k.is_multiple_of(2)) {
+      return false;
+    }
+
+    match self {
+      Number::Big(v) => v.abs().nth_root(k).pow(k) == v.abs(),
+      _ => {
+        let n = self.magnitude();
+        iroot(n, k).checked_pow(k) == Some(n)
+      }
+    }
+  }
+
+  /// Returns whether the number has no fractional part.
+  fn is_whole(&self) -> bool {
+    match self {
+      Number::Float(v) => v.fract() == 0.0,
+      _ => true,
+    }
+  }
+
+  /// Raises the number to the power of `other`, or returns `None` for an integer base with a
+  /// negative exponent. Only a signed (or big) exponent can be negative, so an unsigned exponent
+  /// too large for `u32` still wraps.
+  pub fn pow(&self, other: &Number) -> Option<Number> {
+    if !matches!(self, Number::Float(_)) && *other < Number::from(0i64) {
+      return None;
+    }
+
+    Some(match self {
+      Number::Integer(v, w) => {
+        // wrapping multiplication is the same on the bits of signed and unsigned widths
+        let modulus = BigInt::from(1) << w.bits();
+        let pow = BigInt::from(*v).modpow(&BigInt::from(other.clone()), &modulus);
+        Number::new_integer(pow.to_u128().unwrap(), *w)
+      }
+      Number::Big(v) => Number::Big(v.pow(u32::try_from(BigInt::from(other.clone())).ok()?)),
+      Number::Float(v) => Number::new_float(v.powf(f64::from(other.clone()))),
+    })
+  }
+
+  /// Returns the factorial of the number, which wraps for fixed-width integers.
+  pub fn factorial(&self) -> Number {
+    let mut acc = Number::from(1u64).to_type_of(self);
+    for i in 2..=u64::from(self.clone()) {
+      acc = acc * Number::from(i).to_type_of(self);
+      // wrapped products stay zero and float products stay infinite
+      match acc {
+        Number::Integer(0, _) => break,
+        Number::Float(v) if v.is_infinite() => break,
+        _ => (),
+      }
+    }
+    acc
+  }
+
+  /// Returns the number of ways to choose `k` of the number's items, or `None` if it doesn't
+  /// fit in the number's width.
+  pub fn combinations(&self, k: &Number) -> Option<Number> {
+    self.falling_product(k, false)
+  }
+
+  /// Returns the number of ordered arrangements of `k` of the number's items, or `None` if it
+  /// doesn't fit in the number's width.
+  pub fn permutations(&self, k: &Number) -> Option<Number> {
+    self.falling_product(k, true)
+  }
+
+  /// Multiplies `n (n - 1) ... (n - k + 1)`, dividing by `1 2 ... k` as it goes unless the
+  /// product is `ordered`, so every partial result is exact and no larger than the final one.
+  fn falling_product(&self, k: &Number, ordered: bool) -> Option<Number> {
+    let (n, k) = (BigInt::from(self.clone()), BigInt::from(k.clone()));
+    if k > n {
+      return Some(Number::from(0u64).to_type_of(self));
+    }
+    // C(n, k) = C(n, n - k) takes fewer steps for large k
+    let k = if ordered { k } else { k.clone().min(&n - &k) };
+    let max = match self {
+      Number::Integer(_, w) => Some(BigInt::from(w.max())),
+      _ => None,
+    };
+
+    let (mut acc, mut i) = (BigInt::from(1), BigInt::zero());
+    while i < k {
+      acc *= &n - &i;
+      i += 1;
+      if !ordered {
+        acc /= &i;
+      }
+      if max.as_ref().is_some_and(|max| acc > *max) {
+        return None;
+      }
+    }
+    Some(Number::Big(acc).to_type_of(self))
+  }
+
+  /// Extracts the inclusive bit range `[lo, hi]` of an integer.
+  pub fn bits(&self, hi: u32, lo: u32) -> Number {
+    match self {
+      Number::Integer(v, w) => {
+        let len = hi.saturating_sub(lo) + 1;
+        let mask = 1u128.checked_shl(len).map_or(u128::MAX, |m| m - 1);
+        Number::new_integer(v.checked_shr(lo).unwrap_or(0) & mask, *w)
+      }
+      Number::Big(_) => self.to_width(Width::U64).bits(hi, lo),
+      Number::Float(_) => Number::new_float(f64::NAN),
+    }
+  }
+
+  /// Reverses the order of the low `n` bits of an integer, keeping the higher bits in place.
+  pub fn bitrevn(&self, n: u32) -> Number {
+    match self {
+      Number::Integer(v, w) => {
+        let mask = 1u128.checked_shl(n).map_or(u128::MAX, |m| m - 1);
+        let reversed = (v & mask).reverse_bits().checked_shr(128 - n).unwrap_or(0);
+        Number::new_integer((v & !mask) | reversed, *w)
+      }
+      Number::Big(_) => self.to_width(Width::U64).bitrevn(n),
+      Number::Float(_) => Number::new_float(f64::NAN),
+    }
+  }
+
+  /// Reverses the order of all bits of an integer within its width, or returns `None` for a big
+  /// integer (which has no width) or a float.
+  pub fn reverse_bits(&self) -> Option<Number> {
+    match self {
+      Number::Integer(_, w) => Some(self.bitrevn(w.bits())),
+      Number::Big(_) | Number::Float(_) => None,
+    }
+  }
+
+  /// Sets bit `n` of an integer.
+  pub fn set_bit(&self, n: u32) -> Number {
+    match self {
+      Number::Integer(v, w) => Number::new_integer(v | 1u128.checked_shl(n).unwrap_or(0), *w),
+      Number::Big(_) => self.to_width(Width::U64).set_bit(n),
+      Number::Float(_) => Number::new_float(f64::NAN),
+    }
+  }
+
+  /// Clears bit `n` of an integer.
+  pub fn clear_bit(&self, n: u32) -> Number {
+    match self {
+      Number::Integer(v, w) => Number::new_integer(v & !1u128.checked_shl(n).unwrap_or(0), *w),
+      Number::Big(_) => self.to_width(Width::U64).clear_bit(n),
+      Number::Float(_) => Number::new_float(f64::NAN),
+    }
+  }
+
+  /// Toggles bit `n` of an integer.
+  pub fn toggle_bit(&self, n: u32) -> Number {
+    match self {
+      Number::Integer(v, w) => Number::new_integer(v ^ 1u128.checked_shl(n).unwrap_or(0), *w),
+      Number::Big(_) => self.to_width(Width::U64).toggle_bit(n),
+      Number::Float(_) => Number::new_float(f64::NAN),
+    }
+  }
+
+  /// Returns the XOR of the bytes within the width of the number (floats use all 8 bytes).
+  pub fn xor_bytes(&self) -> Number {
+    let bytes = match self {
+      Number::Integer(v, w) => v.to_le_bytes()[..w.bits() as usize / 8].to_vec(),
+      Number::Big(v) => v.to_signed_bytes_le(),
+      Number::Float(v) => v.to_bits().to_le_bytes().to_vec(),
+    };
+    Number::from(bytes.iter().fold(0u8, |acc, b| acc ^ b))
+  }
+
+  /// Converts an integer to its Gray code, within its width.
+  pub fn gray(&self) -> Number {
+    match self {
+      // the value is stored masked to its width, so the shift is logical within the width
+      Number::Integer(v, w) => Number::new_integer(v ^ (v >> 1), *w),
+      Number::Big(_) => self.to_width(Width::U64).gray(),
+      Number::Float(_) => Number::new_float(f64::NAN),
+    }
+  }
+
+  /// Converts a Gray code back to the integer it encodes.
+  pub fn ungray(&self) -> Number {
+    match self {
+      Number::Integer(v, w) => {
+        let mut acc = *v;
+        let mut shifted = v >> 1;
+        while shifted != 0 {
+          acc ^= shifted;
+          shifted >>= 1;
+        }
+        Number::new_integer(acc, *w)
+      }
+      Number::Big(_) => self.to_width(Width::U64).ungray(),
+      Number::Float(_) => Number::new_float(f64::NAN),
+    }
+  }
+
+  /// Keeps only the lowest set bit, using two's complement negation within the width.
+  pub fn lsb(&self) -> Number {
+    match self {
+      Number::Integer(v, w) => Number::new_integer(v & w.mask(v.wrapping_neg()), *w),
+      Number::Big(_) => self.to_width(Width::U64).lsb(),
+      Number::Float(_) => Number::new_float(f64::NAN),
+    }
+  }
+
+  /// Clears the lowest set bit.
+  pub fn blsr(&self) -> Number {
+    match self {
+      Number::Integer(v, w) => Number::new_integer(v & v.wrapping_sub(1), *w),
+      Number::Big(_) => self.to_width(Width::U64).blsr(),
+      Number::Float(_) => Number::new_float(f64::NAN),
+    }
+  }
+
+  /// Returns the number of differing bits of two integers, compared in the wider of their widths.
+  pub fn hamming(&self, other: &Number) -> Option<Number> {
+    match (self, other) {
+      (Number::Integer(v1, w1), Number::Integer(v2, w2)) => {
+        let diff = number_cast!(*v1, w1, u128) ^ number_cast!(*v2, w2, u128);
+        Some(Number::from(w1.promote(*w2).mask(diff).count_ones() as u64))
+      }
+      (Number::Float(_), _) | (_, Number::Float(_)) => None,
+      _ => self.to_width(Width::U64).hamming(&other.to_width(Width::U64)),
+    }
+  }
+
+  /// Returns the Euclidean remainder, which is never negative (unlike `%`).
+  pub fn rem_euclid(&self, other: &Number) -> Number {
+    match self {
+      Number::Integer(v, w) => {
+        let (v, rhs, w) = match other {
+          // both operands are extended to the promoted width, as for `%`
+          Number::Integer(v2, w2) => (number_cast!(*v, w, u128), number_cast!(*v2, w2, u128), w.promote(*w2)),
+          _ => (*v, u128::from(other.to_width(*w)), *w),
+        };
+        Number::new_integer(integer_op!(v, rhs, w, wrapping_rem_euclid), w)
+      }
+      Number::Big(v) => {
+        let rhs = BigInt::from(other.clone());
+        let rem = v % &rhs;
+        Number::Big(if rem.is_negative() { rem + rhs.abs() } else { rem })
+      }
+      Number::Float(v) => Number::new_float(v.rem_euclid(f64::from(other.clone()))),
     }
   }
 
   pub fn to_signed(&self) -> Number {
     use Width::*;
     match self {
+      // the bit pattern within the width is unchanged
       Number::Integer(v, w) => match w {
-        U64 => Number::new_integer(*v as i64 as u64, I64),
-        U32 => Number::new_integer(*v as i32 as u64, I32),
-        U16 => Number::new_integer(*v as i16 as u64, I16),
-        U8 => Number::new_integer(*v as i8 as u64, I8),
+        U128 => Number::new_integer(*v, I128),
+        U64 => Number::new_integer(*v, I64),
+        U32 => Number::new_integer(*v, I32),
+        U16 => Number::new_integer(*v, I16),
+        U8 => Number::new_integer(*v, I8),
         _ => Number::new_integer(*v, *w),
       },
-      Number::Float(v) => Number::new_integer(*v as i64 as u64, I64),
+      Number::Big(_) => self.to_width(I64),
+      Number::Float(v) => Number::new_integer(*v as i64 as u128, I64),
     }
   }
 
@@ -307,43 +788,67 @@ impl Number {
     use Width::*;
     match self {
       Number::Integer(v, w) => match w {
-        I64 | I32 | I16 | I8 => Number::new_integer(number_cast!(*v, w, u64) * v, *w),
+        I128 => Number::new_integer(*v, U128),
+        I64 => Number::new_integer(*v, U64),
+        I32 => Number::new_integer(*v, U32),
+        I16 => Number::new_integer(*v, U16),
+        I8 => Number::new_integer(*v, U8),
         _ => Number::new_integer(*v, *w),
       },
-      Number::Float(v) => Number::new_integer(*v as u64, U64),
+      Number::Big(_) => self.to_width(U64),
+      Number::Float(v) => Number::new_integer(*v as u64 as u128, U64),
     }
   }
 
   pub fn to_float(&self) -> Number {
     match self {
-      Number::Integer(v, w) => Number::new_float(number_cast!(*v, w, f64)),
       Number::Float(v) => Number::new_float(*v),
+      _ => Number::new_float(f64::from(self.clone())),
     }
   }
 
   pub fn to_width(&self, w: Width) -> Number {
     match self {
-      Number::Integer(v, _) => Number::new_integer(number_cast!(*v, w, u64), w),
-      Number::Float(v) => Number::new_integer(number_cast!(*v, w, u64), w),
+      Number::Integer(v, _) => Number::new_integer(number_cast!(*v, w, u128), w),
+      Number::Big(v) => Number::new_integer(wrap_big(v), w),
+      Number::Float(v) => Number::new_integer(number_cast!(*v, w, u128), w),
+    }
+  }
+
+  /// Converts the number to the same kind (and width) as `other`.
+  pub fn to_type_of(&self, other: &Number) -> Number {
+    match other {
+      Number::Integer(_, w) => self.to_width(*w),
+      Number::Big(_) => Number::Big(BigInt::from(self.clone())),
+      Number::Float(_) => self.to_float(),
     }
   }
 
   pub fn as_pretty_string(&self) -> String {
     match self {
       Number::Integer(v, w) => number_fmt!(*v, w, "{}"),
-      Number::Float(v) => {
-        if v.fract() == 0.0 {
-          format!("{}", *v)
-        } else {
-          format!("{:.2}", *v)
-        }
-      }
+      Number::Big(v) => v.to_string(),
+      Number::Float(v) => match settings().precision {
+        Some(precision) if v.fract() != 0.0 && !settings().roundtrip => format!("{:.*}", precision, *v),
+        // `{}` is the shortest representation that round-trips
+        _ => format!("{}", *v),
+      },
+    }
+  }
+
+  /// Returns the hex form of the number, in uppercase if `upper_hex` is set.
+  pub fn as_hex_string(&self) -> String {
+    if settings().upper_hex {
+      format!("{:#X}", self)
+    } else {
+      format!("{:#x}", self)
     }
   }
 
   pub fn as_string(&self) -> String {
     match self {
       Number::Integer(v, w) => number_fmt!(*v, w, "{}"),
+      Number::Big(v) => v.to_string(),
       Number::Float(v) => format!("{}", v),
     }
   }
@@ -357,9 +862,15 @@ impl Display for Number {
 
 impl std::fmt::Binary for Number {
   fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-    match self {
-      Number::Integer(v, w) => write!(f, "{}", number_fmt!(*v, w, "{:#b}")),
-      Number::Float(v) => write!(f, "{}", v), // no binary for floats
+    match (self, settings().binary_group) {
+      // grouped digits include the leading zeros within the width
+      (Number::Integer(v, w), Some(size)) => {
+        let digits = format!("{:0width$b}", v, width = w.bits() as usize);
+        write!(f, "0b{}", group_digits(&digits, size))
+      }
+      (Number::Integer(v, w), None) => write!(f, "{}", number_fmt!(*v, w, "{:#b}")),
+      (Number::Big(v), _) => write!(f, "{:#b}", v),
+      (Number::Float(v), _) => write!(f, "{}", v), // no binary for floats
     }
   }
 }
@@ -368,6 +879,7 @@ impl std::fmt::Octal for Number {
   fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
     match self {
       Number::Integer(v, w) => write!(f, "{}", number_fmt!(*v, w, "{:#o}")),
+      Number::Big(v) => write!(f, "{:#o}", v),
       Number::Float(v) => write!(f, "{}", v), // no octal for floats
     }
   }
@@ -377,6 +889,17 @@ impl std::fmt::LowerHex for Number {
   fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
     match self {
       Number::Integer(v, w) => write!(f, "{}", number_fmt!(*v, w, "{:#x}")),
+      Number::Big(v) => write!(f, "{:#x}", v),
+      Number::Float(v) => write!(f, "{}", v), // no hex for floats
+    }
+  }
+}
+
+impl std::fmt::UpperHex for Number {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    match self {
+      Number::Integer(v, w) => write!(f, "{}", number_fmt!(*v, w, "{:#X}")),
+      Number::Big(v) => write!(f, "{:#X}", v),
       Number::Float(v) => write!(f, "{}", v), // no hex for floats
     }
   }
@@ -384,13 +907,15 @@ impl std::fmt::LowerHex for Number {
 
 //
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum Width {
+  U128,
   U64,
   U32,
   U16,
   U8,
 
+  I128,
   I64,
   I32,
   I16,
@@ -398,18 +923,20 @@ pub enum Width {
 }
 
 impl Width {
-  pub const fn mask(&self, value: u64) -> u64 {
+  pub const fn mask(&self, value: u128) -> u128 {
     value & self.as_mask()
   }
 
-  pub const fn as_mask(&self) -> u64 {
+  pub const fn as_mask(&self) -> u128 {
     use Width::*;
     match self {
+      U128 => u128::MAX,
       U64 => 0xFFFFFFFFFFFFFFFF,
       U32 => 0xFFFFFFFF,
       U16 => 0xFFFF,
       U8 => 0xFF,
 
+      I128 => u128::MAX,
       I64 => 0xFFFFFFFFFFFFFFFF,
       I32 => 0xFFFFFFFF,
       I16 => 0xFFFF,
@@ -417,14 +944,82 @@ impl Width {
     }
   }
 
+  pub const fn is_signed(&self) -> bool {
+    use Width::*;
+    matches!(self, I128 | I64 | I32 | I16 | I8)
+  }
+
+  /// Returns the largest value of the width.
+  pub const fn max(&self) -> u128 {
+    if self.is_signed() {
+      self.as_mask() >> 1
+    } else {
+      self.as_mask()
+    }
+  }
+
+  pub const fn bits(&self) -> u32 {
+    use Width::*;
+    match self {
+      U128 | I128 => 128,
+      U64 | I64 => 64,
+      U32 | I32 => 32,
+      U16 | I16 => 16,
+      U8 | I8 => 8,
+    }
+  }
+
+  /// Returns the width that mixed-width arithmetic is done in: the wider of the two widths,
+  /// which is signed if either width is signed.
+  pub const fn promote(&self, other: Width) -> Width {
+    use Width::*;
+    let bits = if self.bits() > other.bits() {
+      self.bits()
+    } else {
+      other.bits()
+    };
+    match (bits, self.is_signed() || other.is_signed()) {
+      (128, false) => U128,
+      (64, false) => U64,
+      (32, false) => U32,
+      (16, false) => U16,
+      (8, false) => U8,
+      (128, true) => I128,
+      (64, true) => I64,
+      (32, true) => I32,
+      (16, true) => I16,
+      _ => I8,
+    }
+  }
+
+  pub fn from_name(name: &str) -> Option<Width> {
+    use Width::*;
+    match name {
+      "u128" => Some(U128),
+      "u64" => Some(U64),
+      "u32" => Some(U32),
+      "u16" => Some(U16),
+      "u8" => Some(U8),
+
+      "i128" => Some(I128),
+      "i64" => Some(I64),
+      "i32" => Some(I32),
+      "i16" => Some(I16),
+      "i8" => Some(I8),
+      _ => None,
+    }
+  }
+
   pub fn as_string(&self) -> &str {
     use Width::*;
     match self {
+      U128 => "u128",
       U64 => "u64",
       U32 => "u32",
       U16 => "u16",
       U8 => "u8",
 
+      I128 => "i128",
       I64 => "i64",
       I32 => "i32",
       I16 => "i16",
@@ -499,3 +1094,166 @@ impl Display for Width {
 //     }
 //   }
 // }
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use test_case::test_case;
+
+  #[test_case(Number::from(-1i8) => (0xFF, Width::U8))]
+  #[test_case(Number::from(-1i16) => (0xFFFF, Width::U16))]
+  #[test_case(Number::from(-1i32) => (0xFFFF_FFFF, Width::U32))]
+  #[test_case(Number::from(-1i64) => (u64::MAX as u128, Width::U64))]
+  #[test_case(Number::from(-128i8) => (0x80, Width::U8))]
+  #[test_case(Number::from(5i16) => (5, Width::U16))]
+  #[test_case(Number::from(200u8) => (200, Width::U8))]
+  fn test_to_unsigned(number: Number) -> (u128, Width) {
+    match number.to_unsigned() {
+      Number::Integer(v, w) => (v, w),
+      n => panic!("expected an integer, got {:?}", n),
+    }
+  }
+
+  #[test_case(Number::from(-8i8), 1 => (-4i8 as u8 as u128, Width::I8))]
+  #[test_case(Number::from(-128i8), 7 => (-1i8 as u8 as u128, Width::I8))]
+  #[test_case(Number::from(-128i8), 100 => (-1i8 as u8 as u128, Width::I8); "i8 shift past width")]
+  #[test_case(Number::from(-8i16), 2 => (-2i16 as u16 as u128, Width::I16))]
+  #[test_case(Number::from(-8i32), 3 => (-1i32 as u32 as u128, Width::I32))]
+  #[test_case(Number::from(i64::MIN), 62 => (-2i64 as u64 as u128, Width::I64))]
+  #[test_case(Number::from(64i8), 2 => (16, Width::I8))]
+  #[test_case(Number::from(0x80u8), 1 => (0x40, Width::U8))]
+  #[test_case(Number::from(0x8000u16), 15 => (1, Width::U16))]
+  #[test_case(Number::from(u32::MAX), 4 => (0x0FFF_FFFF, Width::U32))]
+  #[test_case(Number::from(u64::MAX), 63 => (1, Width::U64))]
+  #[test_case(Number::from(u64::MAX), 64 => (0, Width::U64))]
+  #[test_case(Number::from(1u64), 100000000000 => (0, Width::U64))]
+  #[test_case(Number::from(u128::MAX), 127 => (1, Width::U128))]
+  #[test_case(Number::from(u128::MAX), 128 => (0, Width::U128))]
+  #[test_case(Number::from(0xFFu8), 8 => (0, Width::U8))]
+  #[test_case(Number::from(8i8), 8 => (0, Width::I8))]
+  #[test_case(Number::from(-8i8), 100000000000 => (-1i8 as u8 as u128, Width::I8); "i8 shift past u32")]
+  #[test_case(Number::from(i128::MIN), 128 => (u128::MAX, Width::I128))]
+  fn test_shr(number: Number, shift: u64) -> (u128, Width) {
+    match number >> Number::from(shift) {
+      Number::Integer(v, w) => (v, w),
+      n => panic!("expected an integer, got {:?}", n),
+    }
+  }
+
+  #[test_case(Number::from(1u64), 63 => (1 << 63, Width::U64))]
+  #[test_case(Number::from(1u64), 64 => (0, Width::U64))]
+  #[test_case(Number::from(1u64), 200 => (0, Width::U64))]
+  #[test_case(Number::from(1u64), 100000000000 => (0, Width::U64))]
+  #[test_case(Number::from(1u128), 127 => (1 << 127, Width::U128))]
+  #[test_case(Number::from(1u128), 128 => (0, Width::U128))]
+  #[test_case(Number::from(1u8), 7 => (0x80, Width::U8))]
+  #[test_case(Number::from(1u8), 8 => (0, Width::U8))]
+  #[test_case(Number::from(-1i8), 7 => (0x80, Width::I8))]
+  #[test_case(Number::from(-1i8), 8 => (0, Width::I8))]
+  fn test_shl(number: Number, shift: u64) -> (u128, Width) {
+    match number << Number::from(shift) {
+      Number::Integer(v, w) => (v, w),
+      n => panic!("expected an integer, got {:?}", n),
+    }
+  }
+
+  #[test_case(Number::from(-1i8) => ("0xff".to_string(), "0b11111111".to_string()))]
+  #[test_case(Number::from(-2i16) => ("0xfffe".to_string(), format!("0b{}0", "1".repeat(15))))]
+  #[test_case(Number::from(i32::MIN) => ("0x80000000".to_string(), format!("0b1{}", "0".repeat(31))))]
+  #[test_case(Number::from(-1i64) => ("0xffffffffffffffff".to_string(), format!("0b{}", "1".repeat(64))))]
+  #[test_case(Number::from(5i8) => ("0x5".to_string(), "0b101".to_string()))]
+  fn test_signed_bit_pattern(number: Number) -> (String, String) {
+    (format!("{:x}", number), format!("{:b}", number))
+  }
+
+  #[test_case(Number::from(5u8), 4 => "0b0000_0101")]
+  #[test_case(Number::from(0xD6u8), 4 => "0b1101_0110")]
+  #[test_case(Number::from(0x1234u16), 4 => "0b0001_0010_0011_0100")]
+  #[test_case(Number::from(0x1234u16), 8 => "0b00010010_00110100")]
+  #[test_case(Number::from(1u32), 8 => format!("0b{}_{}_{}_00000001", "0".repeat(8), "0".repeat(8), "0".repeat(8)))]
+  #[test_case(Number::from(-1i8), 4 => "0b1111_1111")]
+  #[test_case(Number::from(-2i16), 8 => "0b11111111_11111110")]
+  #[test_case(Number::from(1i32), 8 => format!("0b{}_{}_{}_00000001", "0".repeat(8), "0".repeat(8), "0".repeat(8)); "i32")]
+  #[test_case(Number::from(u64::MAX), 8 => format!("0b{}", ["11111111"; 8].join("_")))]
+  #[test_case(Number::from(1i64), 4 => format!("0b{}_0001", ["0000"; 15].join("_")))]
+  fn test_binary_group(number: Number, size: usize) -> String {
+    use crate::settings::{set_settings, Settings};
+
+    set_settings(Settings {
+      binary_group: Some(size),
+      ..Settings::default()
+    });
+    let grouped = format!("{:b}", number);
+    set_settings(Settings::default());
+    grouped
+  }
+
+  #[test_case(Number::from(0xDEADBEEFu64) => "0xDEADBEEF")]
+  #[test_case(Number::from(-1i16) => "0xFFFF")]
+  #[test_case(Number::from(BigInt::from(u64::MAX) * 16) => "0xFFFFFFFFFFFFFFFF0")]
+  fn test_upper_hex(number: Number) -> String {
+    format!("{:#X}", number)
+  }
+
+  #[test_case(Number::from(0b1101u8) => Some(Number::from(0b1011_0000u8)))]
+  #[test_case(Number::from(-2i16) => Some(Number::from(0x7FFFi16)))]
+  #[test_case(Number::from(BigInt::from(1)) => None; "big integer")]
+  #[test_case(Number::from(1.0) => None; "float")]
+  fn test_reverse_bits(number: Number) -> Option<Number> {
+    number.reverse_bits()
+  }
+
+  #[test]
+  fn test_pretty_precision() {
+    use crate::settings::{set_settings, Settings};
+
+    let pi = Number::from(std::f64::consts::PI);
+    assert_eq!(pi.as_pretty_string(), "3.141592653589793");
+    set_settings(Settings {
+      precision: Some(5),
+      ..Settings::default()
+    });
+    assert_eq!(pi.as_pretty_string(), "3.14159");
+    assert_eq!(Number::from(2.0).as_pretty_string(), "2");
+    set_settings(Settings {
+      precision: Some(0),
+      ..Settings::default()
+    });
+    assert_eq!(pi.as_pretty_string(), "3");
+    set_settings(Settings::default());
+  }
+
+  #[test]
+  fn test_pretty_roundtrip() {
+    use crate::settings::{set_settings, Settings};
+
+    set_settings(Settings {
+      precision: Some(2),
+      ..Settings::default()
+    });
+    assert_eq!(Number::from(0.1).as_pretty_string(), "0.10");
+    set_settings(Settings {
+      roundtrip: true,
+      precision: Some(5),
+      ..Settings::default()
+    });
+    assert_eq!(Number::from(0.1).as_pretty_string(), "0.1");
+    assert_eq!(Number::from(0.1 + 0.2).as_pretty_string(), "0.30000000000000004");
+    let pi = Number::from(std::f64::consts::PI).as_pretty_string();
+    assert_eq!(pi.parse::<f64>(), Ok(std::f64::consts::PI));
+    set_settings(Settings::default());
+  }
+
+  #[test_case(Number::from(200u8), Number::from(100u32) => (300, Width::U32))]
+  #[test_case(Number::from(1u8), Number::from(-2i32) => (-1i32 as u32 as u128, Width::I32))]
+  #[test_case(Number::from(-1i8), Number::from(1u8) => (0, Width::I8))]
+  #[test_case(Number::from(-1i16), Number::from(1u64) => (0, Width::I64))]
+  #[test_case(Number::from(255u8), Number::from(1u8) => (0, Width::U8); "same width wraps")]
+  #[test_case(Number::from(u32::MAX), Number::from(1u16) => (0, Width::U32))]
+  fn test_width_promotion(a: Number, b: Number) -> (u128, Width) {
+    match a + b {
+      Number::Integer(v, w) => (v, w),
+      n => panic!("expected an integer, got {:?}", n),
+    }
+  }
+}
@@ -0,0 +1,172 @@
+use crate::value::{Number, Unit, Value};
+use num_traits::ToPrimitive;
+use serde::{Deserialize, Serialize};
+
+/// The structured form of a value printed by `--json`.
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+pub struct JsonValue {
+  /// The number in the value's unit.
+  pub value: serde_json::Value,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub unit: Option<String>,
+  /// The group of the unit (e.g. `size`).
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub group: Option<String>,
+  #[serde(rename = "type")]
+  pub ty: String,
+  /// Either `integer` or `float`.
+  pub kind: String,
+  /// The width of fixed-width integers (e.g. `u8`).
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub width: Option<String>,
+  // integer representations
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub decimal: Option<String>,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub hex: Option<String>,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub oct: Option<String>,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub bin: Option<String>,
+}
+
+impl From<Value> for JsonValue {
+  fn from(value: Value) -> Self {
+    let unit = (!value.is_raw()).then(|| value.unit.to_string());
+    let group = (!value.is_raw()).then(|| value.unit.group().to_string());
+    let number = Unit::specialize(value.number, value.unit);
+    match &number {
+      Number::Integer(_, w) => JsonValue {
+        // 128-bit values that don't fit in 64 bits are written as strings
+        value: if w.is_signed() {
+          let v = i128::from(number.clone());
+          i64::try_from(v).map_or_else(|_| v.to_string().into(), Into::into)
+        } else {
+          let v = u128::from(number.clone());
+          u64::try_from(v).map_or_else(|_| v.to_string().into(), Into::into)
+        },
+        unit,
+        group,
+        ty: w.to_string(),
+        kind: "integer".to_string(),
+        width: Some(w.to_string()),
+        decimal: Some(number.to_string()),
+        hex: Some(number.as_hex_string()),
+        oct: Some(format!("{:#o}", number)),
+        bin: Some(format!("{:#b}", number)),
+      },
+      // big integers that don't fit in 64 bits are written as strings
+      Number::Big(v) => JsonValue {
+        value: v.to_i64().map_or_else(|| v.to_string().into(), Into::into),
+        unit,
+        group,
+        ty: "bigint".to_string(),
+        kind: "integer".to_string(),
+        width: None,
+        decimal: Some(number.to_string()),
+        hex: Some(number.as_hex_string()),
+        oct: Some(format!("{:#o}", number)),
+        bin: Some(format!("{:#b}", number)),
+      },
+      Number::Float(v) => JsonValue {
+        value: (*v).into(),
+        unit,
+        group,
+        ty: "f64".to_string(),
+        kind: "float".to_string(),
+        width: None,
+        decimal: None,
+        hex: None,
+        oct: None,
+        bin: None,
+      },
+    }
+  }
+}
+
+pub fn json_result(value: Value) -> String {
+  serde_json::to_string(&JsonValue::from(value)).unwrap()
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::value::Width;
+
+  #[test]
+  fn test_json_round_trip() {
+    let json = json_result(Value::new_integer(255, Width::U8));
+    let parsed: JsonValue = serde_json::from_str(&json).unwrap();
+    assert_eq!(parsed, JsonValue::from(Value::new_integer(255, Width::U8)));
+    assert_eq!(parsed.value, 255);
+    assert_eq!(parsed.ty, "u8");
+    assert_eq!(parsed.hex.as_deref(), Some("0xff"));
+    assert_eq!(parsed.bin.as_deref(), Some("0b11111111"));
+
+    let json = json_result(Value::new(Number::from(1.5), Unit::Kilobyte));
+    let parsed: serde_json::Value = serde_json::from_str(&json).unwrap();
+    assert_eq!(
+      parsed,
+      serde_json::json!({"value": 1.5, "unit": "K", "group": "size", "type": "f64", "kind": "float"})
+    );
+  }
+
+  #[test]
+  fn test_json_type_fields() {
+    let parsed = JsonValue::from(Value::new_integer(-5i8 as u128, Width::I8));
+    assert_eq!((parsed.kind.as_str(), parsed.width.as_deref()), ("integer", Some("i8")));
+    assert_eq!((parsed.unit, parsed.group), (None, None));
+
+    let parsed = JsonValue::from(Value::new_float(0.5));
+    assert_eq!((parsed.kind.as_str(), parsed.width.as_deref()), ("float", None));
+    assert_eq!(parsed.group, None);
+
+    let parsed = JsonValue::from(Value::new(Number::from(2048u64), Unit::Byte));
+    assert_eq!(parsed.unit.as_deref(), Some("B"));
+    assert_eq!(parsed.group.as_deref(), Some("size"));
+    assert_eq!(
+      (parsed.kind.as_str(), parsed.width.as_deref()),
+      ("integer", Some("u64"))
+    );
+
+    // a size in a larger unit is a float in that unit
+    let parsed = JsonValue::from(Value::new(Number::from(2u64), Unit::Kilobyte));
+    assert_eq!((parsed.kind.as_str(), parsed.group.as_deref()), ("float", Some("size")));
+
+    let parsed = JsonValue::from(Value::new(Number::from(100.0), Unit::Celsius));
+    assert_eq!(
+      (parsed.kind.as_str(), parsed.group.as_deref()),
+      ("float", Some("temperature"))
+    );
+
+    let json = json_result(Value::new_integer(255, Width::U8));
+    assert!(
+      json.contains(r#""type":"u8","kind":"integer","width":"u8""#),
+      "{}",
+      json
+    );
+  }
+
+  #[test]
+  fn test_json_upper_hex() {
+    use crate::settings::{set_settings, Settings};
+
+    set_settings(Settings {
+      upper_hex: true,
+      ..Settings::default()
+    });
+    let parsed = JsonValue::from(Value::new_integer(0xDEADBEEF, Width::U32));
+    assert_eq!(parsed.hex.as_deref(), Some("0xDEADBEEF"));
+    set_settings(Settings::default());
+  }
+
+  #[test]
+  fn test_json_wide_integer() {
+    let parsed = JsonValue::from(Value::new_integer(1 << 100, Width::U128));
+    assert_eq!(parsed.value, "1267650600228229401496703205376");
+    assert_eq!(parsed.ty, "u128");
+
+    let parsed = JsonValue::from(Value::new_integer(-5i128 as u128, Width::I128));
+    assert_eq!(parsed.value, -5);
+  }
+}
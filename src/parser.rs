@@ -1,15 +1,25 @@
-use crate::functions::{get_constant, get_function, Function};
-use crate::lexer::Token;
-use crate::value::{Unit, Value, Width};
+use crate::env::{define_function, get_user_function, get_variable, remove_variable, set_variable, UserFunction};
+use crate::error::ClcError;
+use crate::functions::{closest_name, get_constant, get_function, get_unit, is_cast, Function};
+use crate::lexer::{Spanned, Token};
+use crate::settings::settings;
+use crate::tree::Node;
+use crate::value::{Number, Unit, Value};
+use num_bigint::BigInt;
 use phf::phf_map;
+use std::cell::Cell;
 
 const PRECEDENCE_TABLE: phf::Map<&'static str, (i32, Assoc)> = phf_map! {
+  "**" => (12, Assoc::Right), // exponentiation
+
   "+u" => (11, Assoc::Right), // unary plus
   "-u" => (11, Assoc::Right), // unary minus
   "!u" => (10, Assoc::Right), // logical not
   "~u" => (10, Assoc::Right), // bitwise not
+  "as" => (10, Assoc::Left),  // cast (keyword)
 
   "*" => (9, Assoc::Left),   // multiplication
+  "*i" => (9, Assoc::Left),  // implicit multiplication
   "/" => (9, Assoc::Left),   // division
   "%" => (9, Assoc::Left),   // modulo
 
@@ -32,6 +42,10 @@ const PRECEDENCE_TABLE: phf::Map<&'static str, (i32, Assoc)> = phf_map! {
 
   "&&" => (1, Assoc::Left),  // logical and
   "||" => (1, Assoc::Left),  // logical or
+  "^^" => (1, Assoc::Left),  // logical xor
+
+  "?" => (0, Assoc::Right),  // conditional (until ':' is found)
+  "?:" => (0, Assoc::Right), // conditional
 
   "(" => (0, Assoc::Right),   // parentheses
 };
@@ -42,24 +56,147 @@ pub enum Assoc {
   Right,
 }
 
+/// Inserts the `*i` operator wherever multiplication is implied (e.g. `2PI`, `3(1+1)`, `2sin(0)`).
+/// A value, constant or `)` followed by a constant, `(` or function call is a multiplication.
+fn insert_implicit_mul(expr: Vec<Spanned>) -> Vec<Spanned> {
+  let is_constant =
+    |t: &Token| matches!(t, Token::Identifier(id) if get_constant(id).or_else(|| get_variable(id)).is_some());
+  let mut result: Vec<Spanned> = vec![];
+
+  for (i, spanned) in expr.iter().enumerate() {
+    let token = &spanned.token;
+    let ends_operand =
+      matches!(result.last().map(|t| &t.token), Some(t) if t.is_value() || t.is_rparen() || is_constant(t));
+    let starts_operand = match token {
+      Token::LParen => true,
+      Token::Identifier(_) if is_constant(token) => true,
+      Token::Identifier(_) => matches!(expr.get(i + 1), Some(t) if t.token.is_lparen()),
+      _ => false,
+    };
+
+    if ends_operand && starts_operand {
+      // the implied operator takes the empty span before the operand
+      result.push(Spanned {
+        token: Token::Operator("*i".to_string()),
+        span: spanned.span.start..spanned.span.start,
+      });
+    }
+    result.push(spanned.clone());
+  }
+  result
+}
+
 /// Converts an infix expression to postfix notation.
 /// It also checks that all identifiers are valid and that the expression is well-formed.
-fn convert_expr_posfix(expr: Vec<Token>) -> Result<Vec<Token>, String> {
+fn convert_expr_posfix(expr: Vec<Spanned>) -> Result<Vec<Token>, ClcError> {
   let mut op_stack: Vec<Token> = vec![];
   let mut rpn_expr: Vec<Token> = vec![];
+  // the positions of the '(' on the operator stack, and the number of arguments within each
+  let mut lparens: Vec<usize> = vec![];
+  let mut arg_counts: Vec<usize> = vec![];
+
+  let mut tokens = expr.into_iter().peekable();
+  let mut after_operand = false;
+  while let Some(Spanned { token, span }) = tokens.next() {
+    let ends_operand = match &token {
+      Token::Value(_) | Token::Literal(_) | Token::RParen => true,
+      Token::Identifier(id) => get_constant(id).or_else(|| get_variable(id)).is_some(),
+      _ => false,
+    };
 
-  for token in expr.into_iter() {
     match token {
-      Token::Value(_) => rpn_expr.push(token),
+      Token::Value(_) | Token::Literal(_) => rpn_expr.push(token),
+      Token::Identifier(id) if id == "as" => {
+        // casts apply after unary operators but before any binary operator
+        let (prec, _) = PRECEDENCE_TABLE["as"];
+        while let Some(Token::Operator(op)) = op_stack.last() {
+          if PRECEDENCE_TABLE[op].0 < prec {
+            break;
+          }
+          rpn_expr.push(op_stack.pop().unwrap());
+        }
+
+        // the cast is applied by the casting function of the same name
+        match tokens.next().map(|t| t.token) {
+          Some(Token::Identifier(name)) if is_cast(&name) => rpn_expr.push(Token::Identifier(name)),
+          _ => return Err(ClcError::Syntax("Expected type after 'as'".to_string())),
+        }
+      }
+      Token::Identifier(id) if id == "to" || id == "in" => {
+        // conversion has the lowest precedence so everything before it is applied first
+        while let Some(t) = op_stack.last() {
+          if t.is_lparen() {
+            break;
+          }
+          rpn_expr.push(op_stack.pop().unwrap());
+        }
+
+        // the conversion is applied by the conversion function of the target unit
+        let unit = match tokens.next().map(|t| t.token) {
+          Some(Token::Identifier(name)) => get_unit(&name),
+          _ => None,
+        };
+        match unit {
+          Some(unit) => rpn_expr.push(Token::Identifier(unit.name().to_string())),
+          None => return Err(ClcError::Syntax(format!("Expected unit after '{}'", id))),
+        }
+      }
       Token::Identifier(id) => {
-        if let Some(value) = get_constant(&id) {
+        if let Some(value) = get_constant(&id).or_else(|| get_variable(&id)) {
           rpn_expr.push(Token::from(value));
-        } else if let Some(_) = get_function(&id) {
+        } else if get_function(&id).is_some() || get_user_function(&id).is_some() {
+          // a function applies to the operand before it (e.g. `5 i8`) or to the one after it
+          let next = tokens.peek().map(|t| &t.token);
+          let has_argument = after_operand
+            || matches!(
+              next,
+              Some(Token::LParen | Token::Value(_) | Token::Literal(_) | Token::Identifier(_))
+            )
+            || matches!(next, Some(Token::Operator(op)) if op.ends_with('u'));
+          if !has_argument {
+            return Err(ClcError::MissingArgument {
+              name: id,
+              position: span.start,
+            });
+          }
           op_stack.push(Token::Identifier(id));
         } else {
-          return Err(format!("Unknown identifier '{}'", id));
+          return Err(ClcError::UnknownIdentifier {
+            suggestion: closest_name(&id).map(str::to_string),
+            name: id,
+            position: span.start,
+          });
         }
       }
+      Token::Operator(op) if op == ":" => {
+        // pop operators off the stack until we find the matching '?'
+        while let Some(t) = op_stack.last() {
+          if t.is_lparen() || matches!(t, Token::Operator(t_op) if t_op == "?") {
+            break;
+          }
+          rpn_expr.push(op_stack.pop().unwrap());
+        }
+
+        if !matches!(op_stack.pop(), Some(Token::Operator(t_op)) if t_op == "?") {
+          return Err(ClcError::UnmatchedConditional(':'));
+        }
+        // the first branch is complete, and is followed by a jump over the second branch
+        rpn_expr.push(Token::Operator(":j".to_string()));
+        op_stack.push(Token::Operator("?:".to_string()));
+      }
+      Token::Operator(op) if op == "=" => {
+        return Err(ClcError::Syntax(
+          "Encountered '=' outside of a function definition".to_string(),
+        ));
+      }
+      Token::Operator(op) if op.ends_with('p') => {
+        // postfix operators bind tightest and apply to the operand that was just output
+        rpn_expr.push(Token::Operator(op));
+      }
+      Token::Operator(op) if op.ends_with('u') => {
+        // prefix unary operators have no left operand so they never pop anything
+        op_stack.push(Token::Operator(op));
+      }
       Token::Operator(op) => {
         // pop operators off the stack until we find one with a lower precedence
         let (prec, assoc) = PRECEDENCE_TABLE[&op];
@@ -70,15 +207,48 @@ fn convert_expr_posfix(expr: Vec<Token>) -> Result<Vec<Token>, String> {
             _ => break,
           };
 
-          if o_prec >= prec && matches!(assoc, Assoc::Left) {
+          if o_prec > prec || (o_prec == prec && matches!(assoc, Assoc::Left)) {
+            check_chained_comparison(&op, op_stack.last())?;
             rpn_expr.push(op_stack.pop().unwrap());
           } else {
             break;
           }
         }
+        check_chained_comparison(&op, op_stack.last())?;
+        if op == "?" {
+          // the condition is complete, and is followed by a jump over the first branch
+          rpn_expr.push(Token::Operator("?j".to_string()));
+        }
         op_stack.push(Token::Operator(op));
       }
-      Token::LParen => op_stack.push(token),
+      Token::LParen => {
+        if lparens.len() >= settings().max_depth.unwrap_or(MAX_NESTING) {
+          return Err(ClcError::Syntax("expression nesting too deep".to_string()));
+        }
+        lparens.push(span.start);
+        arg_counts.push(if matches!(tokens.peek(), Some(t) if t.token.is_rparen()) {
+          0
+        } else {
+          1
+        });
+        op_stack.push(token);
+      }
+      Token::Comma => {
+        // pop operators off the stack until we find the '(' of the enclosing call
+        while let Some(t) = op_stack.last() {
+          if t.is_lparen() {
+            break;
+          }
+          rpn_expr.push(op_stack.pop().unwrap());
+        }
+
+        if op_stack.is_empty() {
+          return Err(ClcError::Syntax("Encountered ',' outside of function call".to_string()));
+        }
+        if let Some(count) = arg_counts.last_mut() {
+          *count += 1;
+        }
+      }
       Token::RParen => {
         // pop operators off the stack until we find a '('
         while let Some(t) = op_stack.pop() {
@@ -91,124 +261,489 @@ fn convert_expr_posfix(expr: Vec<Token>) -> Result<Vec<Token>, String> {
 
         // the stack isn't empty and we didn't find a '(' then there's a mismatched ')'
         if op_stack.is_empty() || !op_stack.last().unwrap().is_lparen() {
-          return Err("Encountered ')' without matching '('".to_string());
+          return Err(ClcError::UnmatchedParen {
+            paren: ')',
+            position: span.start,
+          });
         }
         op_stack.pop();
+        lparens.pop();
+        let arg_count = arg_counts.pop().unwrap();
 
         // if the next token is a function then pop it into the output array
-        if matches!(op_stack.last(), Some(Token::Identifier(_))) {
-          rpn_expr.push(op_stack.pop().unwrap());
+        match op_stack.pop() {
+          Some(Token::Identifier(name))
+            if matches!(get_function(&name), Some(Function::Nullary(_) | Function::Variadic(_))) =>
+          {
+            rpn_expr.push(Token::Call(name, arg_count));
+          }
+          Some(Token::Identifier(name)) => {
+            // a call with the wrong number of arguments would take operands from around it
+            match fixed_arity(&name) {
+              Some(arity) if arity != arg_count => return Err(arity_error(&name, arity, arg_count)),
+              _ => rpn_expr.push(Token::Identifier(name)),
+            }
+          }
+          Some(t) => op_stack.push(t),
+          None => (),
         }
       }
-      Token::Newline => unreachable!(),
+      Token::Newline | Token::Call(..) => unreachable!(),
     }
+    after_operand = ends_operand;
   }
 
   while let Some(t) = op_stack.pop() {
     if t.is_lparen() {
-      return Err("Unmatched ')'".to_string());
+      return Err(ClcError::UnmatchedParen {
+        paren: '(',
+        position: lparens.pop().unwrap(),
+      });
     }
     rpn_expr.push(t);
   }
+
+  if rpn_expr.iter().any(|t| matches!(t, Token::Operator(op) if op == "?")) {
+    return Err(ClcError::UnmatchedConditional('?'));
+  }
   Ok(rpn_expr)
 }
 
+/// Gives an error if a comparison has another comparison as its operand (e.g. `1 == 1 == 1`),
+/// which would compare the 0 or 1 of the inner comparison instead of chaining them.
+fn check_chained_comparison(op: &str, other: Option<&Token>) -> Result<(), ClcError> {
+  let is_equality = |op: &str| matches!(op, "==" | "!=");
+  let is_comparison = |op: &str| is_equality(op) || matches!(op, "<" | ">" | "<=" | ">=");
+  match other {
+    Some(Token::Operator(other)) if is_comparison(op) && is_comparison(other) => {
+      let kind = if is_equality(op) || is_equality(other) {
+        "equality"
+      } else {
+        "comparison"
+      };
+      Err(ClcError::Syntax(format!("chained {} is ambiguous", kind)))
+    }
+    _ => Ok(()),
+  }
+}
+
+fn arity_error(name: &str, expected: usize, got: usize) -> ClcError {
+  ClcError::Arity {
+    name: name.to_string(),
+    expected,
+    got,
+  }
+}
+
+/// Returns the number of arguments a function always takes, or `None` if it isn't a function or
+/// its number of arguments is checked when it is called.
+fn fixed_arity(name: &str) -> Option<usize> {
+  match get_function(name) {
+    _ if get_user_function(name).is_some() => Some(1),
+    Some(Function::Unary(_)) => Some(1),
+    Some(Function::Binary(_)) => Some(2),
+    Some(Function::Ternary(_)) => Some(3),
+    _ => None,
+  }
+}
+
+/// Returns whether the token is one of the jumps around the branches of a conditional.
+fn is_jump(token: &Token) -> bool {
+  matches!(token, Token::Operator(op) if op == "?j" || op == ":j")
+}
+
+/// Returns the index after the `end` operator (`:j` or `?:`) that ends the branch of a
+/// conditional starting at `start`, skipping any conditional nested in the branch.
+fn branch_end(expr: &[Token], start: usize, end: &str) -> usize {
+  let mut depth = 0usize;
+  for (i, token) in expr.iter().enumerate().skip(start) {
+    match token {
+      Token::Operator(op) if op == end && depth == 0 => return i + 1,
+      Token::Operator(op) if op == "?j" => depth += 1,
+      Token::Operator(op) if op == "?:" => depth = depth.saturating_sub(1),
+      _ => (),
+    }
+  }
+  expr.len()
+}
+
+/// Returns an untyped integer literal in the width of a fixed-width integer operand, if the
+/// literal fits in that width.
+fn literal_in_width(literal: Value, typed: &Value) -> Value {
+  match (&literal.number, &typed.number) {
+    (Number::Integer(..), Number::Integer(_, width)) => {
+      // a negated literal has wrapped, so it's read as signed (e.g. `-1` fits in an `i8`)
+      let n = BigInt::from(literal.number.to_signed());
+      let min = if width.is_signed() {
+        -BigInt::from(width.max()) - 1
+      } else {
+        BigInt::from(0)
+      };
+      if n >= min && n <= BigInt::from(width.max()) {
+        Value::from((literal.number.to_width(*width), literal.unit))
+      } else {
+        literal
+      }
+    }
+    _ => literal,
+  }
+}
+
 /// Evaluates a postfix expression and returns the result.
-fn evaluate_expr_postfix(expr: &Vec<Token>) -> Result<Value, String> {
+fn evaluate_expr_postfix(expr: &[Token]) -> Result<Value, ClcError> {
   if expr.is_empty() {
     panic!("empty expression");
   }
 
   let mut stack: Vec<Value> = vec![];
-  let mut nargs: usize = 0;
+  // whether each value on the stack is an untyped integer literal
+  let mut literals: Vec<bool> = vec![];
+  // the height of the stack below each conditional being evaluated
+  let mut conditions: Vec<usize> = vec![];
 
-  for token in expr.into_iter() {
-    if let Token::Value(v) = token {
-      stack.push(*v);
-      nargs += 1;
+  let mut i = 0;
+  while i < expr.len() {
+    let token = &expr[i];
+    i += 1;
+    if let Token::Value(v) | Token::Literal(v) = token {
+      stack.push(v.clone());
+      literals.push(matches!(token, Token::Literal(_)));
       continue;
     }
 
-    let name = match token {
-      Token::Identifier(name) | Token::Operator(name) => name,
+    // only the branch of a conditional that is taken is evaluated
+    match token {
+      Token::Operator(op) if op == "?j" => {
+        let cond = stack.pop().ok_or_else(|| arity_error("?:", 3, 0))?;
+        literals.pop();
+        conditions.push(stack.len());
+        if !bool::from(cond.number) {
+          i = branch_end(expr, i, ":j");
+        }
+        continue;
+      }
+      Token::Operator(op) if op == ":j" || op == "?:" => {
+        let height = conditions.pop().unwrap_or(0);
+        if stack.len() != height + 1 {
+          return Err(arity_error("?:", 3, (stack.len() + 1).saturating_sub(height)));
+        }
+        if op == ":j" {
+          i = branch_end(expr, i, "?:");
+        }
+        continue;
+      }
+      _ => (),
+    }
+
+    let (name, arg_count) = match token {
+      Token::Identifier(name) | Token::Operator(name) => (name, 1),
+      Token::Call(name, arg_count) => (name, *arg_count),
       _ => unreachable!(),
     };
 
+    if let Some(function) = get_user_function(name) {
+      let arg = stack.pop().ok_or_else(|| arity_error(name, 1, 0))?;
+      literals.pop();
+      stack.push(call_user_function(function, arg)?);
+      literals.push(false);
+      continue;
+    }
+
     let func = get_function(name).unwrap();
     match func {
+      Function::Nullary(func) => {
+        if arg_count != 0 {
+          return Err(arity_error(name, 0, arg_count));
+        }
+        stack.push(func()?);
+      }
       Function::Unary(func) => {
-        if nargs < 1 {
-          return Err(format!("Expected one argument to {}", name));
+        if stack.is_empty() {
+          return Err(arity_error(name, 1, stack.len()));
         }
 
         let arg = stack.pop().unwrap();
+        let literal = literals.pop().unwrap_or(false);
         stack.push(func(arg)?);
+        // a sign keeps a literal untyped (e.g. `-1`)
+        literals.push(literal && (name == "-u" || name == "+u"));
       }
       Function::Binary(func) => {
-        if nargs < 2 {
-          return Err(format!("Expected two arguments to {}", name));
+        if stack.len() < 2 {
+          return Err(arity_error(name, 2, stack.len()));
         }
 
         let arg2 = stack.pop().unwrap();
         let arg1 = stack.pop().unwrap();
+        let literal2 = literals.pop().unwrap_or(false);
+        let literal1 = literals.pop().unwrap_or(false);
+        if name == "/" && arg1.unit.is_size() && arg2.unit.is_time() {
+          // dividing a size by a time produces a data rate
+          stack.push(arg1.per_time(arg2)?);
+          literals.push(false);
+          continue;
+        }
+
         let unit = match (arg1.unit, arg2.unit) {
           (Unit::Raw, _) => arg2.unit,
           (_, Unit::Raw) => arg1.unit,
           _ => arg1.unit,
         };
 
-        let arg1 = arg1
-          .convert(unit)
-          .ok_or(format!("Unable to convert {} to {}", arg1.unit, unit))?;
-        let arg2 = arg2
-          .convert(unit)
-          .ok_or(format!("Unable to convert {} to {}", arg2.unit, unit))?;
+        let arg1 = arg1.convert(unit).ok_or(ClcError::IncompatibleUnits {
+          from: arg1.unit,
+          to: unit,
+        })?;
+        let arg2 = arg2.convert(unit).ok_or(ClcError::IncompatibleUnits {
+          from: arg2.unit,
+          to: unit,
+        })?;
+
+        // a percentage of an integer (e.g. `200 * 10%`) is a float instead of being truncated
+        let percent = matches!(expr[..i - 1].last(), Some(Token::Operator(op)) if op == "%p");
+        let arg1 = if percent && arg1.number.is_integer() {
+          Value::from((arg1.number.to_float(), arg1.unit))
+        } else {
+          arg1
+        };
+
+        // an untyped literal takes the width of the other operand (e.g. `u8(255) + 1` wraps)
+        let (arg1, arg2) = match (literal1, literal2) {
+          (true, false) => (literal_in_width(arg1, &arg2), arg2),
+          (false, true) => {
+            let arg2 = literal_in_width(arg2, &arg1);
+            (arg1, arg2)
+          }
+          _ => (arg1, arg2),
+        };
+
+        // a negated literal exponent (e.g. `2 ** -1`) has wrapped, so it's read as signed
+        let negated = matches!(expr[..i - 1].last(), Some(Token::Operator(op)) if op == "-u");
+        let arg2 = if name == "**" && literal2 && negated {
+          Value::from((arg2.number.to_signed(), arg2.unit))
+        } else {
+          arg2
+        };
         stack.push(func(arg1, arg2)?);
-        nargs -= 1; // we popped two but added one back
+      }
+      Function::Ternary(func) => {
+        if stack.len() < 3 {
+          return Err(arity_error(name, 3, stack.len()));
+        }
+
+        let arg3 = stack.pop().unwrap();
+        let arg2 = stack.pop().unwrap();
+        let arg1 = stack.pop().unwrap();
+        literals.truncate(stack.len());
+        stack.push(func(arg1, arg2, arg3)?);
+      }
+      Function::Variadic(func) => {
+        if arg_count == 0 || stack.len() < arg_count {
+          return Err(arity_error(name, arg_count.max(1), stack.len()));
+        }
+
+        let args = stack.split_off(stack.len() - arg_count);
+        literals.truncate(stack.len());
+        stack.push(func(args)?);
       }
     }
+    literals.resize(stack.len(), false);
   }
 
   if stack.len() != 1 {
-    eprintln!("stack: {:?}", stack);
-    panic!("unexpected stack state");
+    return Err(ClcError::Syntax("Expected an operator between values".to_string()));
   }
   let value = stack.pop().unwrap();
-  return Ok(value);
+  Ok(value)
+}
+
+/// Builds the tree of a postfix expression, applying each function to the nodes of its arguments.
+fn build_expr_tree(expr: &[Token]) -> Result<Node, ClcError> {
+  let mut stack: Vec<Node> = vec![];
+
+  for token in expr.iter() {
+    if let Token::Value(v) | Token::Literal(v) = token {
+      stack.push(Node::Value(v.clone()));
+      continue;
+    } else if is_jump(token) {
+      continue;
+    }
+
+    let (name, arg_count) = match token {
+      Token::Identifier(name) | Token::Operator(name) => (name, 1),
+      Token::Call(name, arg_count) => (name, *arg_count),
+      _ => unreachable!(),
+    };
+
+    let arity = match get_function(name) {
+      _ if get_user_function(name).is_some() => 1,
+      Some(Function::Nullary(_)) if arg_count != 0 => return Err(arity_error(name, 0, arg_count)),
+      Some(Function::Nullary(_)) => 0,
+      Some(Function::Unary(_)) => 1,
+      Some(Function::Binary(_)) => 2,
+      Some(Function::Ternary(_)) => 3,
+      Some(Function::Variadic(_)) => arg_count,
+      None => unreachable!(),
+    };
+    if (arity == 0 && !matches!(get_function(name), Some(Function::Nullary(_)))) || stack.len() < arity {
+      return Err(arity_error(name, arity.max(1), stack.len()));
+    }
+
+    let args = stack.split_off(stack.len() - arity);
+    stack.push(Node::Apply(name.clone(), args));
+  }
+
+  if stack.len() != 1 {
+    return Err(ClcError::Syntax("Expected an operator between values".to_string()));
+  }
+  Ok(stack.pop().unwrap())
+}
+
+thread_local! {
+  static CALL_DEPTH: Cell<usize> = const { Cell::new(0) };
+}
+
+/// The deepest nesting of parentheses unless set otherwise, which stops pathological input from
+/// overflowing the stack.
+const MAX_NESTING: usize = 256;
+
+/// The deepest nesting of user function calls unless set otherwise, which stops unbounded
+/// recursion. Each call takes more stack than a parenthesis, so the limit is lower.
+const MAX_CALL_DEPTH: usize = 64;
+
+/// Evaluates the body of a user function with its parameter bound to the argument.
+fn call_user_function(function: UserFunction, arg: Value) -> Result<Value, ClcError> {
+  let depth = CALL_DEPTH.with(|d| d.get());
+  if depth >= settings().max_depth.unwrap_or(MAX_CALL_DEPTH) {
+    return Err(ClcError::Syntax("Maximum function call depth exceeded".to_string()));
+  }
+
+  // the parameter shadows any variable of the same name for the duration of the call
+  let outer = get_variable(&function.param);
+  set_variable(&function.param, arg);
+  CALL_DEPTH.with(|d| d.set(depth + 1));
+  let result = parse(function.body);
+  CALL_DEPTH.with(|d| d.set(depth));
+  match outer {
+    Some(value) => set_variable(&function.param, value),
+    None => remove_variable(&function.param),
+  }
+  result
+}
+
+/// Splits a line of the form `name(param) = body` into its name, parameter and body.
+fn split_definition(expr: &[Spanned]) -> Option<(&str, &str, &[Spanned])> {
+  let tokens = expr.iter().take(5).map(|t| &t.token).collect::<Vec<_>>();
+  match tokens[..] {
+    [Token::Identifier(name), Token::LParen, Token::Identifier(param), Token::RParen, Token::Operator(op)]
+      if op == "=" =>
+    {
+      Some((name, param, &expr[5..]))
+    }
+    _ => None,
+  }
+}
+
+/// Rejects a line break after a binary operator, which would split the operator from its right-hand side.
+fn check_line_ends(tokens: &[Spanned]) -> Result<(), ClcError> {
+  for pair in tokens.windows(2) {
+    match (&pair[0].token, &pair[1].token) {
+      // a dangling '?' is reported as an unmatched conditional
+      (token @ Token::Operator(op), Token::Newline) if token.is_binary_op() && op != "?" => {
+        return Err(ClcError::Syntax(format!("line ends with operator '{}'", op)));
+      }
+      _ => {}
+    }
+  }
+  Ok(())
+}
+
+/// Defines a user function from a line of the form `name(param) = body`.
+fn define_user_function(name: &str, param: &str, body: &[Spanned]) -> Result<(), ClcError> {
+  for id in [name, param] {
+    if get_function(id).is_some() || get_constant(id).is_some() {
+      return Err(ClcError::Redefinition(id.to_string()));
+    }
+  }
+  if body.is_empty() {
+    return Err(ClcError::Syntax("Expected a function body after '='".to_string()));
+  }
+
+  let function = UserFunction {
+    param: param.to_string(),
+    body: body.to_vec(),
+  };
+  define_function(name, function);
+  Ok(())
 }
 
-pub fn parse(tokens: Vec<Token>) -> Result<Value, String> {
+pub fn parse(tokens: Vec<Spanned>) -> Result<Value, ClcError> {
+  parse_last(tokens).map(Option::unwrap_or_default)
+}
+
+/// Evaluates each expression of a program and returns the value of the last one, or `None` if
+/// there is none (e.g. a program of only comments and function definitions).
+pub fn parse_last(tokens: Vec<Spanned>) -> Result<Option<Value>, ClcError> {
+  check_line_ends(&tokens)?;
   let mut values: Vec<Value> = vec![];
-  for expr in tokens.split(|t| t.is_newline()) {
+  for expr in tokens.split(|t| t.token.is_newline()) {
     if expr.is_empty() {
       continue;
     }
 
+    if let Some((name, param, body)) = split_definition(expr) {
+      define_user_function(name, param, body)?;
+      continue;
+    }
+
     // println!("--- tokens ---");
     // println!("infix: {:?}", expr);
-    let rpn_expr = convert_expr_posfix(expr.to_vec())?;
+    let rpn_expr = convert_expr_posfix(insert_implicit_mul(expr.to_vec()))?;
     if rpn_expr.is_empty() {
       // empty expression like "()"
       continue;
     }
 
     // println!("postfix: {:?}", rpn_expr);
+    if rpn_expr.iter().any(is_jump) {
+      // the branch that isn't taken is never evaluated, so its arguments are checked beforehand
+      build_expr_tree(&rpn_expr)?;
+    }
     let value = evaluate_expr_postfix(&rpn_expr)?;
     // println!("value: {}", value.to_string());
     values.push(value);
   }
 
-  let last = values.last();
-  if last.is_some() {
-    return Ok(*last.unwrap());
+  Ok(values.pop())
+}
+
+/// Parses each expression of a program into a tree without evaluating it.
+pub fn parse_tree(tokens: Vec<Spanned>) -> Result<Vec<Node>, ClcError> {
+  check_line_ends(&tokens)?;
+  let mut trees: Vec<Node> = vec![];
+  for expr in tokens.split(|t| t.token.is_newline()) {
+    if expr.is_empty() {
+      continue;
+    }
+
+    if let Some((name, param, body)) = split_definition(expr) {
+      define_user_function(name, param, body)?;
+      continue;
+    }
+
+    let rpn_expr = convert_expr_posfix(insert_implicit_mul(expr.to_vec()))?;
+    if !rpn_expr.is_empty() {
+      trees.push(build_expr_tree(&rpn_expr)?);
+    }
   }
-  return Ok(Value::default());
+  Ok(trees)
 }
 
 #[cfg(test)]
 mod tests {
   use super::*;
   use crate::tokenize;
+  use crate::value::{Number, Width};
   use test_case::test_case;
 
   #[test_case("()" => Ok(Value::new_integer(0, Width::U64)))]
@@ -220,8 +755,339 @@ mod tests {
   #[test_case("sin(deg(90))" => Ok(Value::new_float(1.0)))]
   #[test_case("u32(1)" => Ok(Value::new_integer(1, Width::U32)))]
   #[test_case("u32(1) + 1" => Ok(Value::new_integer(2, Width::U32)))]
+  #[test_case("2 ** 10" => Ok(Value::new_integer(1024, Width::U64)))]
+  #[test_case("2 ** 3 ** 2" => Ok(Value::new_integer(512, Width::U64)))]
+  #[test_case("-2 ** 2" => Ok(Value::new_integer(-4i64 as u128, Width::U64)))]
+  #[test_case("2 * 3 ** 2" => Ok(Value::new_integer(18, Width::U64)))]
+  #[test_case("1.5 ** 2" => Ok(Value::new_float(2.25)))]
+  #[test_case("2.0 ** -1.0" => Ok(Value::new_float(0.5)))]
+  #[test_case("2.0 ** -1i64" => Ok(Value::new_float(0.5)))]
+  #[test_case("4.0 ** -2i8" => Ok(Value::new_float(0.0625)))]
+  #[test_case("2 ** -1i64" => Err("negative exponent requires float base".to_string()))]
+  #[test_case("2 ** -1" => Err("negative exponent requires float base".to_string()); "negated exponent")]
+  #[test_case("2.0 ** -1" => Ok(Value::new_float(0.5)); "negated exponent with float base")]
+  #[test_case("2 ** --1" => Ok(Value::new_integer(2, Width::U64)); "double negated exponent")]
+  #[test_case("2 ** 9223372036854775808" => Ok(Value::new_integer(0, Width::U64)); "huge exponent wraps")]
+  #[test_case("3 ** 9223372036854775809" => Ok(Value::new_integer(3, Width::U64)); "huge exponent odd base")]
+  #[test_case("2i32 ** -3i32" => Err("negative exponent requires float base".to_string()))]
+  #[test_case("50%" => Ok(Value::new_float(0.5)))]
+  #[test_case("10 % 3" => Ok(Value::new_integer(1, Width::U64)))]
+  #[test_case("200 * 10%" => Ok(Value::new_float(20.0)))]
+  #[test_case("200.0 * 10%" => Ok(Value::new_float(20.0)); "float of a percentage")]
+  #[test_case("200 * 10% / 4" => Ok(Value::new_float(5.0)))]
+  #[test_case("200 * 10% + 1" => Ok(Value::new_float(21.0)); "percentage before a binary sign")]
+  #[test_case("10% + 5" => Ok(Value::new_float(5.1)))]
+  #[test_case("200 + 10% + 5" => Ok(Value::new_float(205.1)))]
+  #[test_case("10%-3" => Ok(Value::new_float(-2.9)); "percentage before an adjacent sign")]
+  #[test_case("10 % +3" => Ok(Value::new_integer(1, Width::U64)); "modulo of a positive")]
+  #[test_case("200 * sqrt(4%)" => Ok(Value::new_integer(0, Width::U64)); "percentage not the operand")]
+  #[test_case("10 % -3" => Ok(Value::new_integer(10, Width::U64)); "modulo of a negative")]
+  #[test_case("10i64 % -3" => Ok(Value::new_integer(1, Width::I64)))]
+  #[test_case("(50%) - 1" => Ok(Value::new_float(-0.5)))]
+  #[test_case("-5i8" => Ok(Value::new_integer(-5i8 as u128, Width::I8)))]
+  #[test_case("+5u8" => Ok(Value::new_integer(5, Width::U8)))]
+  #[test_case("-5i8 == -5" => Ok(Value::new_integer(1, Width::U8)))]
+  #[test_case("-5i8 + 200" => Ok(Value::new_integer(195, Width::I64)))]
+  #[test_case("-5i8 + 200i8" => Ok(Value::new_integer(-61i8 as u128, Width::I8)))]
+  #[test_case("256 as u8" => Ok(Value::new_integer(0, Width::U8)))]
+  #[test_case("1 << 200" => Ok(Value::new_integer(0, Width::U64)))]
+  #[test_case("1 >> 100000000000" => Ok(Value::new_integer(0, Width::U64)))]
+  #[test_case("-8i8 >> 100000000000" => Ok(Value::new_integer(-1i8 as u128, Width::I8)))]
+  #[test_case("u128(1) << 100" => Ok(Value::new_integer(1 << 100, Width::U128)))]
+  #[test_case("-(1i128 << 100)" => Ok(Value::new_integer((-1i128 << 100) as u128, Width::I128)))]
+  #[test_case("u64(U64_MAX) + 1u128" => Ok(Value::new_integer(1 << 64, Width::U128)))]
+  #[test_case("18446744073709551616u128" => Ok(Value::new_integer(1 << 64, Width::U128)))]
+  #[test_case("18446744073709551616" => Ok(Value::new_integer(1 << 64, Width::U128)))]
+  #[test_case("340282366920938463463374607431768211455" => Ok(Value::new_integer(u128::MAX, Width::U128)))]
+  #[test_case("0xFFFFFFFFFFFFFFFFFF" => Ok(Value::new_integer((1 << 72) - 1, Width::U128)))]
+  #[test_case("0x0123456789ABCDEF0123456789ABCDEF" => Ok(Value::new_integer(0x0123456789ABCDEF0123456789ABCDEF, Width::U128)))]
+  #[test_case(&("0b1".to_string() + &"0".repeat(64)) => Ok(Value::new_integer(1 << 64, Width::U128)); "65 bit binary")]
+  #[test_case("0x1FFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFF" => Err("Integer literal '0x1FFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFF' does not fit in 128 bits".to_string()))]
+  #[test_case("340282366920938463463374607431768211456" => Err("Integer literal '340282366920938463463374607431768211456' does not fit in 128 bits".to_string()))]
+  #[test_case("3 as f64" => Ok(Value::new_float(3.0)))]
+  #[test_case("-1 as i8" => Ok(Value::new_integer(-1i8 as u128, Width::I8)))]
+  #[test_case("2u64 * 200 as u8" => Ok(Value::new_integer(400, Width::U64)))]
+  #[test_case("(2 * 200) as u8" => Ok(Value::new_integer(144, Width::U8)))]
+  #[test_case("2 as u8 ** 9" => Ok(Value::new_integer(0, Width::U8)))]
+  #[test_case("3 as sin" => Err("Expected type after 'as'".to_string()))]
+  #[test_case("1G / 10s" => Ok(Value::new(Number::from(102.4f64), Unit::MegabytePerSecond)))]
+  #[test_case("512B / 2s" => Ok(Value::new(Number::from(256f64), Unit::BytePerSecond)))]
+  #[test_case("(1G / 10s) to K" => Err("Invalid conversion from M/s to K".to_string()))]
+  #[test_case("1G / 0s" => Err("division by zero".to_string()))]
+  #[test_case("1 / 0" => Err("division by zero".to_string()); "integer division by zero")]
+  #[test_case("1 % 0" => Err("division by zero".to_string()); "integer modulo by zero")]
+  #[test_case("1.0 / 0.0" => Err("division by zero".to_string()); "float division by zero")]
+  #[test_case("1.0 % 0" => Err("division by zero".to_string()); "float modulo by zero")]
+  #[test_case("1 / 0.5" => Ok(Value::new_float(2.0)); "fractional divisor divided as float")]
+  #[test_case("1 % 0.75" => Ok(Value::new_float(0.25)); "fractional divisor remainder as float")]
+  #[test_case("7 / 2.5" => Ok(Value::new_integer(3, Width::U64)); "fractional divisor cast to dividend")]
+  #[test_case("u8(1) / 256" => Ok(Value::new_integer(0, Width::U64)); "divisor wider than dividend")]
+  #[test_case("u8(1) / u16(256)" => Ok(Value::new_integer(0, Width::U16)); "divided in promoted width")]
+  #[test_case("u8(1) % u16(256)" => Ok(Value::new_integer(1, Width::U16)); "remainder in promoted width")]
+  #[test_case("mod(u8(1), u16(256))" => Ok(Value::new_integer(1, Width::U16)); "euclidean remainder in promoted width")]
+  #[test_case("mod(i8(-1), i16(256))" => Ok(Value::new_integer(255, Width::I16)); "signed euclidean remainder")]
+  #[test_case("1 / 0 || 1" => Err("division by zero".to_string()))]
+  #[test_case("1G / 10°C" => Err("Unable to convert °C to G".to_string()))]
+  #[test_case("10s / 1G" => Err("Unable to convert G to s".to_string()))]
+  #[test_case("1G * 10s" => Err("Unable to convert s to G".to_string()))]
+  #[test_case("sin(cos(tan(sqrt(abs(0)))))" => Ok(Value::new_float(0.0f64.cos().sin())))]
+  #[test_case("-sin(-cos(-0.0)) * 2" => Ok(Value::new_float(1f64.sin() * 2.0)))]
+  #[test_case("PI" => Ok(Value::new_float(std::f64::consts::PI)))]
+  #[test_case("sin" => Err("function 'sin' used without an argument at column 1".to_string()))]
+  #[test_case("kilobyte" => Err("function 'kilobyte' used without an argument at column 1".to_string()))]
+  #[test_case("1 + sin" => Err("function 'sin' used without an argument at column 5".to_string()))]
+  #[test_case("(sqrt) * 2" => Err("function 'sqrt' used without an argument at column 2".to_string()))]
+  #[test_case("K" => Err("Expected number before unit".to_string()))]
+  #[test_case("sin - 1" => Err("function 'sin' used without an argument at column 1".to_string()))]
+  #[test_case("2 * flor(1)" => Err("Unknown identifier 'flor' at column 5, did you mean 'floor'?".to_string()))]
+  #[test_case("2 * xyzzy" => Err("Unknown identifier 'xyzzy' at column 5".to_string()))]
+  #[test_case("sin()" => Err("Expected one argument to sin".to_string()))]
+  #[test_case("sin(1, 2)" => Err("Expected one argument to sin".to_string()); "too many arguments")]
+  #[test_case("1 + bits(0xFF, 3)" => Err("Expected three arguments to bits".to_string()); "too few arguments")]
+  #[test_case("f(x) = x\nf(1, 2)" => Err("Expected one argument to f".to_string()); "user function arguments")]
+  #[test_case("1 +" => Err("Expected two arguments to +".to_string()))]
+  #[test_case("clamp(1, 2)" => Err("Expected three arguments to clamp".to_string()))]
+  #[test_case("1 2" => Err("Expected an operator between values".to_string()))]
+  #[test_case("celsius(5K)" => Err("Invalid conversion from K to °C".to_string()))]
+  #[test_case("1 + celsius(5K) * 2" => Err("Invalid conversion from K to °C".to_string()))]
+  #[test_case("kilobyte(1°C) ? 1 : 0" => Err("Invalid conversion from °C to K".to_string()))]
+  #[test_case("100°C to °F" => Ok(Value::new(Number::from(212f64), Unit::Fahrenheit)))]
+  #[test_case("1GB in bytes" => Ok(Value::new(Number::from(1000000000u64), Unit::Byte)))]
+  #[test_case("1.5KB + 500B" => Ok(Value::new(Number::from(2u64), Unit::SiKilobyte)))]
+  #[test_case("1 GiB in bytes" => Ok(Value::new(Number::from(1073741824u64), Unit::Byte)))]
+  #[test_case("1 + 1 to K" => Ok(Value::new(Number::from(2u64), Unit::Kilobyte)))]
+  #[test_case("(2 to K) + 1K" => Ok(Value::new(Number::from(3u64), Unit::Kilobyte)))]
+  #[test_case("100°C to bytes" => Err("Invalid conversion from °C to B".to_string()))]
+  #[test_case("100°C to sin" => Err("Expected unit after 'to'".to_string()))]
+  #[test_case("1 ^^ 0" => Ok(Value::new_integer(1, Width::U8)))]
+  #[test_case("1 ^^ 1" => Ok(Value::new_integer(0, Width::U8)))]
+  #[test_case("1 ^ 1" => Ok(Value::new_integer(0, Width::U64)))]
+  #[test_case("2 ^^ 1 == 1" => Ok(Value::new_integer(0, Width::U8)))]
+  #[test_case("1 + 2 # add them" => Ok(Value::new_integer(3, Width::U64)))]
+  #[test_case("1 < 2 ? 10 : 20" => Ok(Value::new_integer(10, Width::U64)))]
+  #[test_case("1 > 2 ? 10 : 20" => Ok(Value::new_integer(20, Width::U64)))]
+  #[test_case("0 ? 1 : 0 ? 2 : 3" => Ok(Value::new_integer(3, Width::U64)))]
+  #[test_case("1 ? 0 ? 1 : 2 : 3" => Ok(Value::new_integer(2, Width::U64)))]
+  #[test_case("1 ? 2 : 3 + 4" => Ok(Value::new_integer(2, Width::U64)))]
+  #[test_case("(0 ? 2 : 3) + 4" => Ok(Value::new_integer(7, Width::U64)))]
+  #[test_case("0 ? 1/0 : 5" => Ok(Value::new_integer(5, Width::U64)))]
+  #[test_case("1 ? 5 : 1/0" => Ok(Value::new_integer(5, Width::U64)))]
+  #[test_case("1 ? 0 ? 1/0 : 2 : 1/0" => Ok(Value::new_integer(2, Width::U64)); "nested untaken branches")]
+  #[test_case("0 ? (1 ? 1/0 : 1/0) : 3 + 4" => Ok(Value::new_integer(7, Width::U64)))]
+  #[test_case("1 ? 1/0 : 5" => Err("division by zero".to_string()); "taken branch")]
+  #[test_case("1 ? 2 :" => Err("Expected three arguments to ?:".to_string()); "missing second branch")]
+  #[test_case("1 ? 2 : 3 4" => Err("Expected an operator between values".to_string()); "untaken branch")]
+  #[test_case("1 == 1 == 1" => Err("chained equality is ambiguous".to_string()))]
+  #[test_case("1 != 2 != 1" => Err("chained equality is ambiguous".to_string()))]
+  #[test_case("1 < 2 == 1" => Err("chained equality is ambiguous".to_string()); "relational then equality")]
+  #[test_case("0 == 2 < 3" => Err("chained equality is ambiguous".to_string()); "equality then relational")]
+  #[test_case("1 < 2 < 3" => Err("chained comparison is ambiguous".to_string()))]
+  #[test_case("3 > 2 >= 1" => Err("chained comparison is ambiguous".to_string()))]
+  #[test_case("(1 == 1) == 1" => Ok(Value::new_integer(1, Width::U8)))]
+  #[test_case("1 < 2 && 2 < 3" => Ok(Value::new_integer(1, Width::U8)))]
+  #[test_case("1 + 1 == 2 ? 3 < 4 : 0" => Ok(Value::new_integer(1, Width::U8)))]
+  #[test_case("1 ? 2" => Err("Encountered '?' without matching ':'".to_string()); "missing colon")]
+  #[test_case("u8(255) + 1" => Ok(Value::new_integer(0, Width::U8)); "literal takes typed width")]
+  #[test_case("u8(255) + 300" => Ok(Value::new_integer(555, Width::U64)); "literal too wide for typed width")]
+  #[test_case("i8(10) + -1" => Ok(Value::new_integer(9, Width::I8)); "negative literal in signed width")]
+  #[test_case("u8(255) == 255" => Ok(Value::new_integer(1, Width::U8)); "literal compared in typed width")]
+  #[test_case("1 : 2" => Err("Encountered ':' without matching '?'".to_string()); "missing question mark")]
   fn test_parse(input: &str) -> Result<Value, String> {
-    let tokens = tokenize(input)?;
-    parse(tokens)
+    tokenize(input).and_then(parse).map_err(|err| err.to_string())
+  }
+
+  #[test_case("18446744073709551615" => Width::U64)]
+  #[test_case("18446744073709551616" => Width::U128)]
+  #[test_case("340282366920938463463374607431768211455" => Width::U128)]
+  #[test_case("0xFFFFFFFFFFFFFFFF" => Width::U64)]
+  #[test_case("0xFFFFFFFFFFFFFFFFFF" => Width::U128)]
+  #[test_case("0x0123456789ABCDEF0123456789ABCDEF" => Width::U128)]
+  #[test_case("0o2000000000000000000000" => Width::U128)]
+  #[test_case("u32(1) + 1" => Width::U32)]
+  #[test_case("1 + u8(255)" => Width::U8)]
+  #[test_case("u8(255) + 300" => Width::U64)]
+  #[test_case("i8(10) + -1" => Width::I8)]
+  #[test_case("u8(200) + u32(100)" => Width::U32)]
+  #[test_case("u8(10) & 0xFF" => Width::U8)]
+  #[test_case("2 * 3" => Width::U64)]
+  #[test_case("-5i8 == -5" => Width::U8)]
+  #[test_case("1.5 < 2.5" => Width::U8)]
+  #[test_case("u8(1) / u16(256)" => Width::U16)]
+  #[test_case("mod(i8(-1), i16(256))" => Width::I16)]
+  #[test_case("ord('A')" => Width::U64)]
+  #[test_case("ord(chr(66))" => Width::U64)]
+  fn test_parse_width(input: &str) -> Width {
+    tokenize(input).and_then(parse).unwrap().number.width()
+  }
+
+  #[test_case("sin" => ClcError::MissingArgument { name: "sin".to_string(), position: 0 })]
+  #[test_case("1 + qwzx" => ClcError::UnknownIdentifier { name: "qwzx".to_string(), position: 4, suggestion: None })]
+  #[test_case("sinn(0)" => ClcError::UnknownIdentifier { name: "sinn".to_string(), position: 0, suggestion: Some("sin".to_string()) })]
+  #[test_case("clamp(1, 2)" => ClcError::Arity { name: "clamp".to_string(), expected: 3, got: 2 })]
+  #[test_case("(1 + 2" => ClcError::UnmatchedParen { paren: '(', position: 0 }; "unmatched open paren")]
+  #[test_case("1 + 2)" => ClcError::UnmatchedParen { paren: ')', position: 5 }; "unmatched close paren")]
+  #[test_case("1 ? 2" => ClcError::UnmatchedConditional('?'))]
+  #[test_case("1G * 10s" => ClcError::IncompatibleUnits { from: Unit::Second, to: Unit::Gigabyte })]
+  #[test_case("c2f(212°F)" => ClcError::UnsupportedConversion { from: Unit::Fahrenheit, to: Unit::Fahrenheit })]
+  #[test_case("1 % 0" => ClcError::DivisionByZero)]
+  fn test_parse_error_variants(input: &str) -> ClcError {
+    tokenize(input).and_then(parse).unwrap_err()
+  }
+
+  #[test_case("5 *\n- 3" => "line ends with operator '*'"; "before unary minus")]
+  #[test_case("1 +\n" => "line ends with operator '+'"; "trailing newline")]
+  #[test_case("2 <<\n3" => "line ends with operator '<<'")]
+  #[test_case("1\n2 &&\n3" => "line ends with operator '&&'"; "later line")]
+  fn test_parse_line_end(input: &str) -> String {
+    tokenize(input).and_then(parse).unwrap_err().to_string()
+  }
+
+  #[test]
+  fn test_parse_tree_line_end() {
+    let err = tokenize("5 *\n- 3").and_then(parse_tree).unwrap_err();
+    assert_eq!(err.to_string(), "line ends with operator '*'");
+  }
+
+  #[test_case("f(x) = x*x + 1\nf(3) == 10" => Ok(Value::new_integer(1, Width::U8)); "define and call")]
+  #[test_case("f(x) = x*x + 1\n2f(3)" => Ok(Value::new_integer(20, Width::U64)); "implicit multiplication")]
+  #[test_case("hyp(x) = sqrt(x*x + 9)\nhyp(4)" => Ok(Value::new_float(5.0)); "body calls a built-in")]
+  #[test_case("sq(x) = x*x\nquad(x) = sq(sq(x))\nquad(3)" => Ok(Value::new_integer(81, Width::U64)); "nested calls")]
+  #[test_case("f(s) = s*2\nf(3s) to s" => Ok(Value::new(Number::from(6u64), Unit::Second)); "parameter named s")]
+  #[test_case("f(x) = x + 1\nf(x) = x + 2\nf(1)" => Ok(Value::new_integer(3, Width::U64)); "redefine user function")]
+  #[test_case("f(x) = x\nf(1) + x" => Err("Unknown identifier 'x' at column 17, did you mean 'E'?".to_string()); "parameter is scoped to call")]
+  #[test_case("sqrt(x) = x" => Err("Cannot redefine built-in 'sqrt'".to_string()); "redefine built-in")]
+  #[test_case("f(PI) = PI" => Err("Cannot redefine built-in 'PI'".to_string()); "constant parameter")]
+  #[test_case("f(x) =" => Err("Expected a function body after '='".to_string()); "missing body")]
+  #[test_case("f(x) = f(x)\nf(1)" => Err("Maximum function call depth exceeded".to_string()); "unbounded recursion")]
+  #[test_case("1 = 2" => Err("Encountered '=' outside of a function definition".to_string()); "stray equals")]
+  fn test_parse_user_functions(input: &str) -> Result<Value, String> {
+    tokenize(input).and_then(parse).map_err(|err| err.to_string())
+  }
+
+  #[test_case("--5" => Ok(Value::new_integer(5, Width::U64)); "double negation")]
+  #[test_case("~~5" => Ok(Value::new_integer(5, Width::U64)); "double bitwise not")]
+  #[test_case("!!1" => Ok(Value::new_integer(1, Width::U8)); "double logical not")]
+  #[test_case("-+-5" => Ok(Value::new_integer(5, Width::U64)); "mixed signs")]
+  #[test_case("-~0" => Ok(Value::new_integer(1, Width::U64)); "negate bitwise not")]
+  #[test_case("~-1" => Ok(Value::new_integer(0, Width::U64)); "bitwise not negate")]
+  #[test_case("2 * --3" => Ok(Value::new_integer(6, Width::U64)); "after binary operator")]
+  #[test_case("--5 == 5" => Ok(Value::new_integer(1, Width::U8)); "in comparison")]
+  fn test_parse_stacked_unary(input: &str) -> Result<Value, String> {
+    tokenize(input).and_then(parse).map_err(|err| err.to_string())
+  }
+
+  #[test_case("2PI" => Ok(Value::new_integer(6, Width::U64)); "value and constant")]
+  #[test_case("2PI == 2 * PI" => Ok(Value::new_integer(1, Width::U8)); "same as explicit multiplication")]
+  #[test_case("2.0PI" => Ok(Value::new_float(2.0 * std::f64::consts::PI)); "float and constant")]
+  #[test_case("3(1+1)" => Ok(Value::new_integer(6, Width::U64)); "value and parens")]
+  #[test_case("(1+1)(2+1)" => Ok(Value::new_integer(6, Width::U64)); "parens and parens")]
+  #[test_case("2sin(0)" => Ok(Value::new_integer(0, Width::U64)); "value and function call")]
+  #[test_case("PI(2)" => Ok(Value::new_float(2.0 * std::f64::consts::PI)); "constant and parens")]
+  #[test_case("sin(0)" => Ok(Value::new_integer(0, Width::U64)); "function call")]
+  fn test_parse_implicit_mul(input: &str) -> Result<Value, String> {
+    tokenize(input).and_then(parse).map_err(|err| err.to_string())
+  }
+
+  #[test_case("10k == 10000" => Ok(Value::new_integer(1, Width::U8)))]
+  #[test_case("2g" => Ok(Value::new_integer(2_000_000_000, Width::U64)))]
+  #[test_case("2.5m" => Ok(Value::new_float(2_500_000.0)))]
+  #[test_case("2 k" => Err("Unknown identifier 'k' at column 3, did you mean 'E'?".to_string()); "separated suffix is an identifier")]
+  fn test_parse_si_multiplier(input: &str) -> Result<Value, String> {
+    tokenize(input).and_then(parse).map_err(|err| err.to_string())
+  }
+
+  #[test]
+  fn test_parse_tree_structure() {
+    let int = |v| Node::Value(Value::new_integer(v, Width::U64));
+    let trees = tokenize("1 + 2 * 3").and_then(parse_tree).unwrap();
+    assert_eq!(
+      trees,
+      vec![Node::Apply(
+        "+".to_string(),
+        vec![int(1), Node::Apply("*".to_string(), vec![int(2), int(3)])]
+      )]
+    );
+  }
+
+  #[test_case("1 + 2 * 3" => Ok("+\n|-- 1\n`-- *\n    |-- 2\n    `-- 3".to_string()))]
+  #[test_case("(1 + 2) * 3" => Ok("*\n|-- +\n|   |-- 1\n|   `-- 2\n`-- 3".to_string()))]
+  #[test_case("-abs(2PI)" => Ok("-\n`-- abs\n    `-- *\n        |-- 2\n        `-- 3.141592653589793".to_string()))]
+  #[test_case("1 ? 2 : 3" => Ok("?:\n|-- 1\n|-- 2\n`-- 3".to_string()))]
+  #[test_case("1 +" => Err("Expected two arguments to +".to_string()))]
+  #[test_case("1 + bits(0xFF, 3)" => Err("Expected three arguments to bits".to_string()))]
+  fn test_parse_tree_display(input: &str) -> Result<String, String> {
+    tokenize(input)
+      .and_then(parse_tree)
+      .map(|trees| trees.iter().map(|tree| tree.to_string()).collect())
+      .map_err(|err| err.to_string())
+  }
+
+  #[test]
+  fn test_max_depth() {
+    use crate::settings::{set_settings, Settings};
+
+    let nested = |depth| format!("{}1{}", "(".repeat(depth), ")".repeat(depth));
+    assert_eq!(
+      tokenize(&nested(256)).and_then(parse),
+      Ok(Value::new_integer(1, Width::U64))
+    );
+    let err = ClcError::Syntax("expression nesting too deep".to_string());
+    assert_eq!(tokenize(&nested(257)).and_then(parse), Err(err.clone()));
+    assert_eq!(tokenize(&nested(100_000)).and_then(parse_tree), Err(err.clone()));
+
+    set_settings(Settings {
+      max_depth: Some(2),
+      ..Settings::default()
+    });
+    assert_eq!(
+      tokenize("((1)) + (2)").and_then(parse),
+      Ok(Value::new_integer(3, Width::U64))
+    );
+    assert_eq!(tokenize("abs((-(1)))").and_then(parse), Err(err));
+    let recursion = tokenize("f(x) = x > 0 ? f(x - 1) : 0\nf(3)").and_then(parse);
+    assert_eq!(
+      recursion,
+      Err(ClcError::Syntax("Maximum function call depth exceeded".to_string()))
+    );
+    set_settings(Settings::default());
+  }
+
+  #[test_case("1+2*3" => "1 + (2 * 3)"; "precedence")]
+  #[test_case("(1 + 2) * 3" => "(1 + 2) * 3")]
+  #[test_case("1 - 2 - 3" => "(1 - 2) - 3"; "left associative")]
+  #[test_case("2 ** 3 ** 2" => "2 ** (3 ** 2)"; "right associative")]
+  #[test_case("-(1+2)" => "-(1 + 2)")]
+  #[test_case("1 + -5i8" => "1 + (-5i8)")]
+  #[test_case("2(3)" => "2 * 3"; "implicit multiplication")]
+  #[test_case("50% * 2.0" => "(50%) * 2.0")]
+  #[test_case("roundn(2.5,1 )+sin(0.5)" => "roundn(2.5, 1) + sin(0.5)")]
+  #[test_case("256 as u8" => "u8(256)")]
+  #[test_case("1.5K to B" => "bytes(1.5K)")]
+  #[test_case("1?2:3" => "1 ? 2 : 3"; "conditional")]
+  #[test_case("1>2 ? 3 : 4" => "(1 > 2) ? 3 : 4")]
+  fn test_parse_tree_canonical(input: &str) -> String {
+    tokenize(input)
+      .and_then(parse_tree)
+      .map(|trees| trees[0].canonical())
+      .unwrap_or_else(|err| err.to_string())
+  }
+
+  #[test]
+  fn test_canonical_round_trip() {
+    for input in [
+      "1+2*3",
+      "-5i8 * 2 - u16(7) % 3",
+      "2 ** -1.5",
+      "sqrt(16) << 2 | 1",
+      "(1 + 2)% * 3.0",
+    ] {
+      let canonical = tokenize(input).and_then(parse_tree).unwrap()[0].canonical();
+      assert_eq!(
+        tokenize(&canonical).and_then(parse),
+        tokenize(input).and_then(parse),
+        "{}",
+        canonical
+      );
+    }
   }
 }
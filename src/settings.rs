@@ -0,0 +1,47 @@
+use crate::number::Width;
+use std::cell::Cell;
+
+/// Options that change how expressions are evaluated.
+#[derive(Copy, Clone, Debug, Default)]
+pub struct Settings {
+  /// Subtraction that underflows an unsigned width produces a signed result.
+  pub signed_sub: bool,
+  /// Integer arithmetic that overflows its width is an error instead of wrapping.
+  pub checked: bool,
+  /// Integer arithmetic that overflows its width clamps to the width's minimum or maximum.
+  pub saturating: bool,
+  /// Bare integer literals are arbitrary-precision instead of 64-bit.
+  pub bigint: bool,
+  /// Warnings are recorded for surprising evaluation semantics.
+  pub warn: bool,
+  /// The number of decimals shown for floats with a fraction, or all of them if unset.
+  pub precision: Option<usize>,
+  /// Floats are shown with the shortest representation that parses back to the same value.
+  pub roundtrip: bool,
+  /// Hex digits are shown in uppercase (e.g. `0xDEADBEEF`).
+  pub upper_hex: bool,
+  /// The width of bare integer literals, or u64 if unset.
+  pub width: Option<Width>,
+  /// Integer literals may also be written as `$FF`, `0FFh` or `1101b`.
+  pub asm_syntax: bool,
+  /// Binary digits are separated into groups of this many with `_`.
+  pub binary_group: Option<usize>,
+  /// The seed of the random number generator, or the system clock if unset.
+  pub seed: Option<u64>,
+  /// The deepest nesting of parentheses and of user function calls, or 256 and 64 if unset.
+  pub max_depth: Option<usize>,
+}
+
+thread_local! {
+  static SETTINGS: Cell<Settings> = Cell::new(Settings::default());
+}
+
+/// Returns the settings used by the current thread.
+pub fn settings() -> Settings {
+  SETTINGS.with(|s| s.get())
+}
+
+/// Replaces the settings used by the current thread.
+pub fn set_settings(settings: Settings) {
+  SETTINGS.with(|s| s.set(settings))
+}
@@ -0,0 +1,96 @@
+use crate::number::{Number, Width};
+use crate::unit::Unit;
+use crate::value::Value;
+use std::fmt;
+
+/// A node of an expression tree.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Node {
+  Value(Value),
+  /// A function or operator applied to its arguments.
+  Apply(String, Vec<Node>),
+}
+
+impl Node {
+  /// Returns the name shown for the node, with internal operators shown as they are written.
+  fn label(&self) -> String {
+    match self {
+      Node::Value(value) => value.to_string(),
+      Node::Apply(name, _) => match name.as_str() {
+        "-u" => "-".to_string(),
+        "*i" => "*".to_string(),
+        "%p" => "%".to_string(),
+        _ => name.clone(),
+      },
+    }
+  }
+
+  /// Returns the expression as infix with normalized spacing, where every operation nested in
+  /// another operation is parenthesized to make the precedence explicit (e.g. `1 + (2 * 3)`).
+  /// Casts and conversions are written as calls of their functions (e.g. `u8(256)`).
+  pub fn canonical(&self) -> String {
+    self.infix(true)
+  }
+
+  fn infix(&self, top: bool) -> String {
+    let text = match self {
+      Node::Value(value) => {
+        let text = literal(value);
+        // a negative literal is parenthesized like the unary minus it is read as
+        if top || !text.starts_with('-') {
+          return text;
+        }
+        text
+      }
+      Node::Apply(name, args) => match (name.as_str(), &args[..]) {
+        ("-u" | "+u" | "!u" | "~u", [arg]) => format!("{}{}", &name[..1], arg.infix(false)),
+        ("%p", [arg]) => format!("{}%", arg.infix(false)),
+        ("?:", [cond, a, b]) => format!("{} ? {} : {}", cond.infix(false), a.infix(false), b.infix(false)),
+        (_, [a, b]) if !name.starts_with(|c: char| c.is_alphanumeric() || c == '_') => {
+          format!("{} {} {}", a.infix(false), self.label(), b.infix(false))
+        }
+        _ => {
+          let args = args.iter().map(|arg| arg.infix(true)).collect::<Vec<_>>();
+          return format!("{}({})", name, args.join(", "));
+        }
+      },
+    };
+
+    if top {
+      text
+    } else {
+      format!("({})", text)
+    }
+  }
+
+  fn fmt_children(&self, f: &mut fmt::Formatter<'_>, prefix: &str) -> fmt::Result {
+    if let Node::Apply(_, args) = self {
+      for (i, arg) in args.iter().enumerate() {
+        let last = i == args.len() - 1;
+        writeln!(f)?;
+        write!(f, "{}{}{}", prefix, if last { "`-- " } else { "|-- " }, arg.label())?;
+        arg.fmt_children(f, &format!("{}{}", prefix, if last { "    " } else { "|   " }))?;
+      }
+    }
+    Ok(())
+  }
+}
+
+/// Returns a literal that reads back as the value, with a type suffix for integers of another
+/// width than `u64` and a decimal point for whole floats.
+fn literal(value: &Value) -> String {
+  let number = Unit::specialize(value.number.clone(), value.unit);
+  let text = match &number {
+    Number::Integer(_, Width::U64) | Number::Big(_) => number.to_string(),
+    Number::Integer(_, width) => format!("{}{}", number, width),
+    Number::Float(v) => format!("{:?}", v),
+  };
+  format!("{}{}", text, value.unit)
+}
+
+impl fmt::Display for Node {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    write!(f, "{}", self.label())?;
+    self.fmt_children(f, "")
+  }
+}
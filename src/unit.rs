@@ -10,10 +10,29 @@ pub enum Unit {
   Gigabyte,
   Terabyte,
   Petabyte,
+  // decimal (SI) digital size
+  SiKilobyte,
+  SiMegabyte,
+  SiGigabyte,
+  SiTerabyte,
+  SiPetabyte,
   // temperature
   Celsius,
   Fahrenheit,
   Kelvin,
+  // time
+  Second,
+  // character (an integer code point shown as its character)
+  Char,
+  // IPv4 address (an integer shown as a dotted quad)
+  Ipv4,
+  // data rate
+  BytePerSecond,
+  KilobytePerSecond,
+  MegabytePerSecond,
+  GigabytePerSecond,
+  TerabytePerSecond,
+  PetabytePerSecond,
 }
 
 impl Unit {
@@ -25,6 +44,29 @@ impl Unit {
     matches!(
       self,
       Unit::Byte | Unit::Kilobyte | Unit::Megabyte | Unit::Gigabyte | Unit::Terabyte | Unit::Petabyte
+    ) || self.is_si_size()
+  }
+
+  pub fn is_si_size(&self) -> bool {
+    matches!(
+      self,
+      Unit::SiKilobyte | Unit::SiMegabyte | Unit::SiGigabyte | Unit::SiTerabyte | Unit::SiPetabyte
+    )
+  }
+
+  pub fn is_time(&self) -> bool {
+    matches!(self, Unit::Second)
+  }
+
+  pub fn is_datarate(&self) -> bool {
+    matches!(
+      self,
+      Unit::BytePerSecond
+        | Unit::KilobytePerSecond
+        | Unit::MegabytePerSecond
+        | Unit::GigabytePerSecond
+        | Unit::TerabytePerSecond
+        | Unit::PetabytePerSecond
     )
   }
 
@@ -32,7 +74,88 @@ impl Unit {
     match self {
       Unit::Raw => "raw",
       Unit::Byte | Unit::Kilobyte | Unit::Megabyte | Unit::Gigabyte | Unit::Terabyte | Unit::Petabyte => "size",
+      Unit::SiKilobyte | Unit::SiMegabyte | Unit::SiGigabyte | Unit::SiTerabyte | Unit::SiPetabyte => "si size",
       Unit::Celsius | Unit::Fahrenheit | Unit::Kelvin => "temperature",
+      Unit::Second => "time",
+      Unit::Char => "char",
+      Unit::Ipv4 => "ipv4",
+      Unit::BytePerSecond
+      | Unit::KilobytePerSecond
+      | Unit::MegabytePerSecond
+      | Unit::GigabytePerSecond
+      | Unit::TerabytePerSecond
+      | Unit::PetabytePerSecond => "datarate",
+    }
+  }
+
+  /// Returns the unit that values of this unit's group are stored in.
+  pub fn base(&self) -> Unit {
+    if self.is_size() {
+      Unit::Byte
+    } else if self.is_datarate() {
+      Unit::BytePerSecond
+    } else {
+      *self
+    }
+  }
+
+  /// Returns the name of the function used to convert to this unit.
+  pub fn name(&self) -> &'static str {
+    match self {
+      Unit::Raw => "raw",
+      Unit::Byte => "bytes",
+      Unit::Kilobyte => "kilobyte",
+      Unit::Megabyte => "megabyte",
+      Unit::Gigabyte => "gigabyte",
+      Unit::Terabyte => "terabyte",
+      Unit::Petabyte => "petabyte",
+      Unit::SiKilobyte => "si_kilobyte",
+      Unit::SiMegabyte => "si_megabyte",
+      Unit::SiGigabyte => "si_gigabyte",
+      Unit::SiTerabyte => "si_terabyte",
+      Unit::SiPetabyte => "si_petabyte",
+      Unit::Celsius => "celsius",
+      Unit::Fahrenheit => "fahrenheit",
+      Unit::Kelvin => "kelvin",
+      Unit::Second => "seconds",
+      Unit::Char => "chr",
+      Unit::Ipv4 => "ipv4",
+      Unit::BytePerSecond => "bytes_per_sec",
+      Unit::KilobytePerSecond => "kilobyte_per_sec",
+      Unit::MegabytePerSecond => "megabyte_per_sec",
+      Unit::GigabytePerSecond => "gigabyte_per_sec",
+      Unit::TerabytePerSecond => "terabyte_per_sec",
+      Unit::PetabytePerSecond => "petabyte_per_sec",
+    }
+  }
+
+  /// Returns the unit for the name of a conversion function.
+  pub fn from_name(name: &str) -> Option<Unit> {
+    match name {
+      "bytes" => Some(Unit::Byte),
+      "kilobyte" => Some(Unit::Kilobyte),
+      "megabyte" => Some(Unit::Megabyte),
+      "gigabyte" => Some(Unit::Gigabyte),
+      "terabyte" => Some(Unit::Terabyte),
+      "petabyte" => Some(Unit::Petabyte),
+      "si_kilobyte" => Some(Unit::SiKilobyte),
+      "si_megabyte" => Some(Unit::SiMegabyte),
+      "si_gigabyte" => Some(Unit::SiGigabyte),
+      "si_terabyte" => Some(Unit::SiTerabyte),
+      "si_petabyte" => Some(Unit::SiPetabyte),
+      "celsius" => Some(Unit::Celsius),
+      "fahrenheit" => Some(Unit::Fahrenheit),
+      "kelvin" => Some(Unit::Kelvin),
+      "seconds" => Some(Unit::Second),
+      "chr" => Some(Unit::Char),
+      "ipv4" => Some(Unit::Ipv4),
+      "bytes_per_sec" => Some(Unit::BytePerSecond),
+      "kilobyte_per_sec" => Some(Unit::KilobytePerSecond),
+      "megabyte_per_sec" => Some(Unit::MegabytePerSecond),
+      "gigabyte_per_sec" => Some(Unit::GigabytePerSecond),
+      "terabyte_per_sec" => Some(Unit::TerabytePerSecond),
+      "petabyte_per_sec" => Some(Unit::PetabytePerSecond),
+      _ => None,
     }
   }
 
@@ -48,7 +171,19 @@ impl Unit {
       Unit::Gigabyte => (number * Number::from(1024u64.pow(3))).to_unsigned(),
       Unit::Terabyte => (number * Number::from(1024u64.pow(4))).to_unsigned(),
       Unit::Petabyte => (number * Number::from(1024u64.pow(5))).to_unsigned(),
+      Unit::SiKilobyte => (number * Number::from(1000u64)).to_unsigned(),
+      Unit::SiMegabyte => (number * Number::from(1000u64.pow(2))).to_unsigned(),
+      Unit::SiGigabyte => (number * Number::from(1000u64.pow(3))).to_unsigned(),
+      Unit::SiTerabyte => (number * Number::from(1000u64.pow(4))).to_unsigned(),
+      Unit::SiPetabyte => (number * Number::from(1000u64.pow(5))).to_unsigned(),
       Unit::Celsius | Unit::Fahrenheit | Unit::Kelvin => number.to_float(),
+      // data rate (base unit is bytes per second)
+      Unit::BytePerSecond => number.to_float(),
+      Unit::KilobytePerSecond => number.to_float() * Number::from(1024u64),
+      Unit::MegabytePerSecond => number.to_float() * Number::from(1024u64.pow(2)),
+      Unit::GigabytePerSecond => number.to_float() * Number::from(1024u64.pow(3)),
+      Unit::TerabytePerSecond => number.to_float() * Number::from(1024u64.pow(4)),
+      Unit::PetabytePerSecond => number.to_float() * Number::from(1024u64.pow(5)),
       _ => number,
     }
   }
@@ -64,6 +199,17 @@ impl Unit {
       Unit::Gigabyte => number.to_float() / Number::from(1024u64.pow(3)),
       Unit::Terabyte => number.to_float() / Number::from(1024u64.pow(4)),
       Unit::Petabyte => number.to_float() / Number::from(1024u64.pow(5)),
+      Unit::SiKilobyte => number.to_float() / Number::from(1000u64),
+      Unit::SiMegabyte => number.to_float() / Number::from(1000u64.pow(2)),
+      Unit::SiGigabyte => number.to_float() / Number::from(1000u64.pow(3)),
+      Unit::SiTerabyte => number.to_float() / Number::from(1000u64.pow(4)),
+      Unit::SiPetabyte => number.to_float() / Number::from(1000u64.pow(5)),
+      // data rate (base unit is bytes per second)
+      Unit::KilobytePerSecond => number.to_float() / Number::from(1024u64),
+      Unit::MegabytePerSecond => number.to_float() / Number::from(1024u64.pow(2)),
+      Unit::GigabytePerSecond => number.to_float() / Number::from(1024u64.pow(3)),
+      Unit::TerabytePerSecond => number.to_float() / Number::from(1024u64.pow(4)),
+      Unit::PetabytePerSecond => number.to_float() / Number::from(1024u64.pow(5)),
       _ => number,
     }
   }
@@ -80,6 +226,8 @@ impl Unit {
       (a, b) if a == b => Some(value),
       // size (all stored as bytes)
       (a, b) if a.is_size() && b.is_size() => Some(value),
+      // data rate (all stored as bytes per second)
+      (a, b) if a.is_datarate() && b.is_datarate() => Some(value),
       // temperature
       (Unit::Celsius, Unit::Fahrenheit) => Some(value.to_float() * Number::from(9f64 / 5f64) + Number::from(32f64)),
       (Unit::Celsius, Unit::Kelvin) => Some(value.to_float() + Number::from(273.15f64)),
@@ -95,6 +243,18 @@ impl Unit {
     }
   }
 
+  /// Returns the largest unit of a group that a number (in the group's base unit) is at least one of.
+  pub fn fit(group: &str, number: &Number) -> Unit {
+    let units = Unit::for_group(group);
+    units
+      .iter()
+      .rev()
+      .find(|unit| f64::from(Unit::specialize(number.clone(), **unit)).abs() >= 1.0)
+      .copied()
+      .unwrap_or(units[0])
+  }
+
+  #[allow(clippy::should_implement_trait)]
   pub fn from_str(s: &str) -> Option<Unit> {
     match s {
       // size
@@ -104,10 +264,17 @@ impl Unit {
       "G" => Some(Unit::Gigabyte),
       "T" => Some(Unit::Terabyte),
       "P" => Some(Unit::Petabyte),
+      "KB" => Some(Unit::SiKilobyte),
+      "MB" => Some(Unit::SiMegabyte),
+      "GB" => Some(Unit::SiGigabyte),
+      "TB" => Some(Unit::SiTerabyte),
+      "PB" => Some(Unit::SiPetabyte),
       // temperature
       "°" | "°C" => Some(Unit::Celsius),
       "°F" => Some(Unit::Fahrenheit),
       "°K" => Some(Unit::Kelvin),
+      // time
+      "s" => Some(Unit::Second),
       _ => None,
     }
   }
@@ -123,10 +290,38 @@ impl Unit {
         Unit::Terabyte,
         Unit::Petabyte,
       ],
+      "si size" => vec![
+        Unit::Byte,
+        Unit::SiKilobyte,
+        Unit::SiMegabyte,
+        Unit::SiGigabyte,
+        Unit::SiTerabyte,
+        Unit::SiPetabyte,
+      ],
       "temperature" => vec![Unit::Celsius, Unit::Fahrenheit, Unit::Kelvin],
+      "time" => vec![Unit::Second],
+      "char" => vec![Unit::Char],
+      "ipv4" => vec![Unit::Ipv4],
+      "datarate" => vec![
+        Unit::BytePerSecond,
+        Unit::KilobytePerSecond,
+        Unit::MegabytePerSecond,
+        Unit::GigabytePerSecond,
+        Unit::TerabytePerSecond,
+        Unit::PetabytePerSecond,
+      ],
       _ => vec![],
     }
   }
+
+  /// Returns the units of a group with the favorites in the group first, in the order given.
+  pub fn for_group_with_favorites(group: &str, favorites: &[Unit]) -> Vec<Unit> {
+    let units = Unit::for_group(group);
+    let (mut first, rest): (Vec<Unit>, Vec<Unit>) = units.into_iter().partition(|unit| favorites.contains(unit));
+    first.sort_by_key(|unit| favorites.iter().position(|favorite| favorite == unit));
+    first.extend(rest);
+    first
+  }
 }
 
 impl std::fmt::Display for Unit {
@@ -140,10 +335,26 @@ impl std::fmt::Display for Unit {
       Unit::Gigabyte => write!(f, "G"),
       Unit::Terabyte => write!(f, "T"),
       Unit::Petabyte => write!(f, "P"),
+      Unit::SiKilobyte => write!(f, "KB"),
+      Unit::SiMegabyte => write!(f, "MB"),
+      Unit::SiGigabyte => write!(f, "GB"),
+      Unit::SiTerabyte => write!(f, "TB"),
+      Unit::SiPetabyte => write!(f, "PB"),
       // temperature
       Unit::Celsius => write!(f, "°C"),
       Unit::Fahrenheit => write!(f, "°F"),
       Unit::Kelvin => write!(f, "°K"),
+      // time
+      Unit::Second => write!(f, "s"),
+      Unit::Char => write!(f, "chr"),
+      Unit::Ipv4 => write!(f, "ipv4"),
+      // data rate
+      Unit::BytePerSecond => write!(f, "B/s"),
+      Unit::KilobytePerSecond => write!(f, "K/s"),
+      Unit::MegabytePerSecond => write!(f, "M/s"),
+      Unit::GigabytePerSecond => write!(f, "G/s"),
+      Unit::TerabytePerSecond => write!(f, "T/s"),
+      Unit::PetabytePerSecond => write!(f, "P/s"),
     }
   }
 }
@@ -1,8 +1,9 @@
+use crate::error::ClcError;
 pub use crate::number::{Number, Width};
 pub use crate::unit::Unit;
 
 /// A value is a number plus a unit.
-#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[derive(Clone, Debug, PartialEq, Eq)]
 pub struct Value {
   pub number: Number,
   pub unit: Unit,
@@ -24,12 +25,13 @@ impl Value {
     Self { number, unit }
   }
 
-  pub const fn new_integer(value: u64, width: Width) -> Self {
+  pub const fn new_integer(value: u128, width: Width) -> Self {
     let number = Number::new_integer(value, width);
     let unit = Unit::Raw;
     Self { number, unit }
   }
 
+  #[allow(dead_code)]
   pub const fn new_float(value: f64) -> Self {
     let number = Number::new_float(value);
     let unit = Unit::Raw;
@@ -44,8 +46,19 @@ impl Value {
     self.unit.is_raw()
   }
 
-  pub fn convert(self, unit: Unit) -> Option<Self> {
-    let number = Unit::convert(self.number, self.unit, unit)?;
+  /// Divides a size by a time, producing a data rate in the unit that best fits it.
+  pub fn per_time(self, time: Value) -> Result<Self, ClcError> {
+    if !bool::from(time.number.clone()) {
+      return Err(ClcError::DivisionByZero);
+    }
+
+    let number = self.number.to_float() / time.number.to_float();
+    let unit = Unit::fit("datarate", &number);
+    Ok(Self { number, unit })
+  }
+
+  pub fn convert(&self, unit: Unit) -> Option<Self> {
+    let number = Unit::convert(self.number.clone(), self.unit, unit)?;
     Some(Self { number, unit })
   }
 }
@@ -68,9 +81,31 @@ impl From<(Number, Unit)> for Value {
   }
 }
 
+impl From<Value> for bool {
+  fn from(value: Value) -> Self {
+    bool::from(value.number)
+  }
+}
+
 impl std::fmt::Display for Value {
   fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-    let number = Unit::specialize(self.number, self.unit);
+    // characters and addresses are shown as their number if arithmetic took them out of range
+    match self.unit {
+      Unit::Char => {
+        return match self.number.to_ascii() {
+          Some(c) => write!(f, "{}", c),
+          None => write!(f, "{}", self.number.as_pretty_string()),
+        }
+      }
+      Unit::Ipv4 => {
+        return match self.number.to_ipv4() {
+          Some(addr) => write!(f, "{}", addr),
+          None => write!(f, "{}", self.number.as_pretty_string()),
+        }
+      }
+      _ => (),
+    }
+    let number = Unit::specialize(self.number.clone(), self.unit);
     write!(f, "{}{}", number.as_pretty_string(), self.unit)
   }
 }
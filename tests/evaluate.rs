@@ -0,0 +1,42 @@
+use clc::{evaluate, ClcError, Number, Unit, Value, Width};
+
+#[test]
+fn test_evaluate() {
+  assert_eq!(evaluate("1 + 2"), Ok(Value::new_integer(3, Width::U64)));
+  assert_eq!(evaluate("2K to B"), Ok(Value::new(Number::from(2048u64), Unit::Byte)));
+  assert_eq!(evaluate("1 +").unwrap_err().to_string(), "Expected two arguments to +");
+}
+
+#[test]
+fn test_evaluate_error_variants() {
+  let arity = ClcError::Arity {
+    name: "+".to_string(),
+    expected: 2,
+    got: 1,
+  };
+  assert_eq!(evaluate("1 +"), Err(arity));
+  assert!(matches!(evaluate("1 + foo"), Err(ClcError::UnknownIdentifier { name, position: 4, .. }) if name == "foo"));
+  assert!(matches!(
+    evaluate("((1 + 2)"),
+    Err(ClcError::UnmatchedParen {
+      paren: '(',
+      position: 0
+    })
+  ));
+  assert!(matches!(
+    evaluate("1 + 2)"),
+    Err(ClcError::UnmatchedParen {
+      paren: ')',
+      position: 5
+    })
+  ));
+  assert_eq!(evaluate("1 / 0"), Err(ClcError::DivisionByZero));
+  assert!(matches!(evaluate("1 $ 2"), Err(ClcError::Lex { slice, position: 2 }) if slice == "$"));
+  assert_eq!(
+    evaluate("celsius(5K)"),
+    Err(ClcError::Conversion {
+      from: Unit::Kilobyte,
+      to: Unit::Celsius
+    })
+  );
+}